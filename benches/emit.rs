@@ -0,0 +1,64 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use eventd::event;
+
+event!(Plain => Fn(x: u64) + Send + Sync + 'static);
+event!(Shared SharedEvent => Fn(x: u64) + Send + Sync + 'static);
+event!(Atomic AtomicEvent => Fn(x: u64) + Send + Sync + 'static);
+
+const HANDLERS: usize = 16;
+
+fn emit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("emit");
+
+    let mut plain = Plain::default();
+    let shared = SharedEvent::default();
+    let atomic = AtomicEvent::default();
+    for _ in 0..HANDLERS {
+        let _ = plain.subscribe(|x| {
+            black_box(x);
+        });
+        let _ = shared.subscribe(|x| {
+            black_box(x);
+        });
+        let _ = atomic.subscribe(|x| {
+            black_box(x);
+        });
+    }
+
+    group.bench_function("plain", |b| b.iter(|| plain.emit(black_box(42))));
+    group.bench_function("shared", |b| b.iter(|| shared.emit(black_box(42))));
+    group.bench_function("atomic", |b| b.iter(|| atomic.emit(black_box(42))));
+    group.finish();
+}
+
+fn subscribe(c: &mut Criterion) {
+    let mut group = c.benchmark_group("subscribe_unsubscribe");
+
+    let mut plain = Plain::default();
+    let shared = SharedEvent::default();
+    let atomic = AtomicEvent::default();
+    for _ in 0..HANDLERS {
+        let _ = plain.subscribe(|_| {});
+        let _ = shared.subscribe(|_| {});
+        let _ = atomic.subscribe(|_| {});
+    }
+
+    group.bench_function("plain", |b| {
+        b.iter(|| {
+            let subscription = plain.subscribe(|_| {});
+            plain.unsubscribe(subscription).unwrap()
+        })
+    });
+    group.bench_function("shared", |b| {
+        b.iter(|| shared.unsubscribe(shared.subscribe(|_| {})).unwrap())
+    });
+    group.bench_function("atomic", |b| {
+        b.iter(|| atomic.unsubscribe(atomic.subscribe(|_| {})).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, emit, subscribe);
+criterion_main!(benches);
@@ -0,0 +1,448 @@
+//! Attribute macros defining [eventd](https://docs.rs/eventd) event dispatchers from traits.
+//!
+//! The macros are re-exported as `eventd::events` and `eventd::listeners` when `derive` feature
+//! of `eventd` is enabled, generated code refers to `eventd` crate by that name.
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote, ToTokens};
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::{
+    bracketed, Attribute, Error, FnArg, Generics, Ident, ItemTrait, Lifetime, Pat, PatIdent,
+    ReturnType, Signature, TraitBoundModifier, TraitItem, Type, TypeParamBound, Visibility,
+};
+
+mod listeners;
+
+
+/// Defines event dispatchers from methods of a trait.
+///
+/// Every method of the trait declares an event: its arguments are event arguments and its return
+/// type is return type of handlers. Methods taking `&self` declare events with `Fn` handlers,
+/// methods taking `&mut self` declare events with `FnMut` handlers emitted through mutable
+/// reference.
+///
+/// The trait is replaced with a struct of the same name, visibility and generics, which holds
+/// dispatcher of every event in a field named after its method. Dispatcher types are named after
+/// the trait and the method, `resized` method of `WindowEvents` trait is dispatched by
+/// `WindowEventsResized`. Doc comments of the trait and its methods are kept on generated types.
+///
+/// Supertraits of the trait, like `Send + Sync + 'a`, are bounds of all handlers. Handlers
+/// must be `'static` if supertraits don't include a lifetime.
+///
+/// Dispatchers are defined with `event!`, so they have all its methods, and flavor of
+/// dispatchers can be given as argument of the attribute, like `#[events(Shared)]` or
+/// `#[events(Keyed[u32])]`.
+///
+/// ```
+/// use std::cell::Cell;
+/// use std::fmt::Display;
+/// use eventd::{events, Propagation};
+///
+/// /// Events of a window showing values of type `T`.
+/// #[events]
+/// pub trait WindowEvents<'a, T>: 'a
+/// where
+///     T: Clone + Display,
+/// {
+///     /// Window was resized.
+///     fn resized(&self, width: u32, height: u32);
+///
+///     /// Window displays a new value.
+///     fn shown(&mut self, value: &T) -> Propagation;
+///
+///     fn closing(&self) -> bool;
+/// }
+///
+/// let area = Cell::new(0);
+/// let mut events = WindowEvents::<String>::default();
+/// let _ = events.resized.subscribe(|width, height| area.set(width * height));
+/// let _ = events.shown.subscribe(|value| {
+///     println!("Showing {}", value);
+///     Propagation::Stop
+/// });
+/// let _ = events.closing.subscribe(|| true);
+///
+/// events.resized.emit(4, 3);
+/// assert_eq!(area.get(), 12);
+/// assert!(events.shown.emit(&"text".to_string()));
+/// assert_eq!(events.closing.emit_collect(), vec![true]);
+/// ```
+///
+/// Methods declaring events can't have default implementations or generic parameters:
+///
+/// ```compile_fail
+/// #[eventd::events]
+/// trait Events {
+///     fn changed<T>(&self, value: T);
+/// }
+/// ```
+///
+/// Methods must take `self` by reference:
+///
+/// ```compile_fail
+/// #[eventd::events]
+/// trait Events {
+///     fn closed(self);
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn events(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let flavor = if attr.is_empty() { Ok(None) } else { syn::parse(attr).map(Some) };
+    let result = flavor.and_then(|flavor| expand(flavor, syn::parse(item)?));
+    result.unwrap_or_else(Error::into_compile_error).into()
+}
+
+
+/// Defines dispatcher of calls to listener objects implementing a trait.
+///
+/// The trait is kept as is and a dispatcher named after it, `WindowListenerDispatcher` for
+/// `WindowListener` trait, is defined with visibility and generics of the trait. Dispatcher
+/// stores listeners subscribed with `subscribe`, `subscribe_with_priority` or `subscribe_scoped`
+/// and implements the trait itself: every call of its method is forwarded to all listeners in
+/// order of descending priority, then in order of subscription.
+///
+/// Methods must take `&self` or `&mut self`; dispatcher method taking `&mut self` is allowed to
+/// call listeners mutably. Methods returning `Propagation` are forwarded until one of listeners
+/// stops propagation, which is returned to the caller, other methods can't return values.
+/// Arguments must be `Clone`, they are cloned for every listener but the last one.
+///
+/// Listeners must be `'static`, or outlive the lifetime bounding the trait, like
+/// `trait Listener<'a, T: 'a>: 'a`; type parameters of such trait must outlive it as well.
+///
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use eventd::listeners;
+///
+/// #[listeners]
+/// pub trait WindowListener {
+///     fn resized(&self, width: u32, height: u32);
+///     fn closed(&self);
+/// }
+///
+/// struct Logger(Rc<RefCell<Vec<String>>>);
+///
+/// impl WindowListener for Logger {
+///     fn resized(&self, width: u32, height: u32) {
+///         self.0.borrow_mut().push(format!("resized to {}x{}", width, height));
+///     }
+///
+///     fn closed(&self) {
+///         self.0.borrow_mut().push("closed".into());
+///     }
+/// }
+///
+/// let log = Rc::new(RefCell::new(Vec::new()));
+/// let mut listeners = WindowListenerDispatcher::default();
+/// let subscription = listeners.subscribe(Logger(log.clone()));
+/// listeners.resized(640, 480);
+/// listeners.unsubscribe(subscription).unwrap();
+/// listeners.closed();
+/// assert_eq!(*log.borrow(), vec!["resized to 640x480"]);
+/// ```
+///
+/// Methods returning other values can't be forwarded to several listeners:
+///
+/// ```compile_fail
+/// #[eventd::listeners]
+/// trait Listener {
+///     fn closing(&self) -> bool;
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn listeners(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let attr = TokenStream2::from(attr);
+    let result = if attr.is_empty() {
+        syn::parse(item).and_then(listeners::expand)
+    } else {
+        Err(Error::new_spanned(attr, "`listeners` attribute takes no arguments"))
+    };
+    result.unwrap_or_else(Error::into_compile_error).into()
+}
+
+
+/// Event declared by trait method.
+struct Event {
+    attrs: Vec<Attribute>,
+    field: Ident,
+    name: Ident,
+    mutable: bool,
+    args: Vec<(Ident, Type)>,
+    ret: Option<Type>,
+}
+
+impl Event {
+    fn parse(owner: &Ident, item: TraitItem) -> syn::Result<Self> {
+        let method = match item {
+            TraitItem::Fn(method) => method,
+            item => return Err(Error::new_spanned(item, "events trait can only declare methods")),
+        };
+        if let Some(body) = &method.default {
+            return Err(Error::new_spanned(body, "event methods can't have default implementation"));
+        }
+        check_signature(&method.sig, "event")?;
+        let sig = method.sig;
+        let mutable = is_mutable(&sig, "event")?;
+        let args = sig.inputs.into_iter().skip(1).map(|input| match input {
+            FnArg::Typed(arg) => match *arg.pat {
+                Pat::Ident(PatIdent { by_ref: None, ident, subpat: None, .. }) => {
+                    Ok((ident, *arg.ty))
+                }
+                pat => Err(Error::new_spanned(pat, "event arguments must be plain names")),
+            },
+            FnArg::Receiver(receiver) => {
+                Err(Error::new_spanned(receiver, "unexpected `self` argument"))
+            }
+        });
+        let ret = match sig.output {
+            ReturnType::Default => None,
+            ReturnType::Type(_, ty) => match *ty {
+                Type::Tuple(tuple) if tuple.elems.is_empty() => None,
+                ty => Some(ty),
+            },
+        };
+        Ok(Event {
+            attrs: method.attrs,
+            name: format_ident!("{}{}", owner, camel_case(&sig.ident), span = sig.ident.span()),
+            field: sig.ident,
+            mutable,
+            args: args.collect::<syn::Result<_>>()?,
+            ret,
+        })
+    }
+}
+
+
+/// Rejects method signatures which can't be dispatched, `what` names methods in errors.
+fn check_signature(sig: &Signature, what: &str) -> syn::Result<()> {
+    if let Some(token) = sig.constness {
+        return Err(Error::new_spanned(token, format!("{} methods can't be `const`", what)));
+    }
+    if let Some(token) = sig.asyncness {
+        return Err(Error::new_spanned(token, format!("{} methods can't be `async`", what)));
+    }
+    if let Some(token) = sig.unsafety {
+        return Err(Error::new_spanned(token, format!("{} methods can't be `unsafe`", what)));
+    }
+    if let Some(abi) = &sig.abi {
+        return Err(Error::new_spanned(abi, format!("{} methods can't declare ABI", what)));
+    }
+    if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+        let message = format!("{} methods can't be generic", what);
+        return Err(Error::new_spanned(&sig.generics, message));
+    }
+    if let Some(variadic) = &sig.variadic {
+        return Err(Error::new_spanned(variadic, format!("{} methods can't be variadic", what)));
+    }
+    Ok(())
+}
+
+
+/// Checks whether method takes `&mut self` rather than `&self`.
+fn is_mutable(sig: &Signature, what: &str) -> syn::Result<bool> {
+    let message = format!("{} methods must take `&self` or `&mut self`", what);
+    match sig.inputs.first() {
+        Some(FnArg::Receiver(receiver))
+            if receiver.reference.is_some() && receiver.colon_token.is_none() =>
+        {
+            Ok(receiver.mutability.is_some())
+        }
+        Some(FnArg::Receiver(receiver)) => Err(Error::new_spanned(receiver, message)),
+        _ => Err(Error::new(sig.ident.span(), message)),
+    }
+}
+
+
+/// Parses every item, reporting errors of all of them at once.
+fn parse_all<T, U>(
+    items: impl IntoIterator<Item = T>,
+    mut parse: impl FnMut(T) -> syn::Result<U>,
+) -> syn::Result<Vec<U>> {
+    let mut parsed = Vec::new();
+    let mut error: Option<Error> = None;
+    for item in items {
+        match parse(item) {
+            Ok(item) => parsed.push(item),
+            Err(err) => match &mut error {
+                Some(error) => error.combine(err),
+                None => error = Some(err),
+            },
+        }
+    }
+    match error {
+        Some(error) => Err(error),
+        None => Ok(parsed),
+    }
+}
+
+
+/// Names type parameter of generated methods `base`, followed by number if trait already has
+/// type parameter of that name.
+fn free_type_param(generics: &Generics, base: &str) -> Ident {
+    (0..)
+        .map(|n: u32| match n {
+            0 => format_ident!("{}", base),
+            n => format_ident!("{}{}", base, n),
+        })
+        .find(|ident| generics.type_params().all(|param| param.ident != *ident))
+        .expect("generics are finite")
+}
+
+
+/// Checks whether type is `Propagation`, `eventd::Propagation` or `::eventd::Propagation`.
+///
+/// Other paths ending with `Propagation` name types of their own.
+fn is_propagation(path: &syn::TypePath) -> bool {
+    let segments = &path.path.segments;
+    let names = match (segments.len(), path.path.leading_colon) {
+        (1, None) => ["Propagation"].as_slice(),
+        (2, _) => ["eventd", "Propagation"].as_slice(),
+        _ => return false,
+    };
+    path.qself.is_none()
+        && segments
+            .iter()
+            .zip(names)
+            .all(|(segment, name)| segment.ident == name && segment.arguments.is_none())
+}
+
+
+/// Converts `snake_case` method name to `CamelCase` part of dispatcher name.
+fn camel_case(ident: &Ident) -> String {
+    ident
+        .unraw()
+        .to_string()
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+
+/// Dispatcher flavor given as argument of `events` attribute, with key type of `Keyed` flavor.
+struct Flavor {
+    name: Ident,
+    key: Option<TokenStream2>,
+}
+
+impl Parse for Flavor {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: Ident = input.parse()?;
+        let key = match name.to_string().as_str() {
+            "Keyed" => {
+                let content;
+                let brackets = bracketed!(content in input);
+                let key: Type = content.parse()?;
+                let mut group = TokenStream2::new();
+                brackets.surround(&mut group, |tokens| key.to_tokens(tokens));
+                Some(group)
+            }
+            "Reentrant" | "Shared" | "Atomic" | "Queued" | "Topic" => None,
+            _ => {
+                let message =
+                    "expected `Reentrant`, `Shared`, `Atomic`, `Queued`, `Keyed[Key]` or `Topic`";
+                return Err(Error::new_spanned(name, message));
+            }
+        };
+        Ok(Flavor { name, key })
+    }
+}
+
+
+fn expand(flavor: Option<Flavor>, item: ItemTrait) -> syn::Result<TokenStream2> {
+    if let Some(token) = item.unsafety {
+        return Err(Error::new_spanned(token, "events trait can't be `unsafe`"));
+    }
+    if let Some(token) = item.auto_token {
+        return Err(Error::new_spanned(token, "events trait can't be `auto`"));
+    }
+    // Bounds are passed to `event!`, which takes them as single tokens.
+    let mut bounds = Vec::new();
+    for bound in item.supertraits {
+        match &bound {
+            TypeParamBound::Trait(trait_bound)
+                if matches!(trait_bound.modifier, TraitBoundModifier::Maybe(_)) =>
+            {
+                return Err(Error::new_spanned(bound, "handler bounds can't be relaxed"));
+            }
+            TypeParamBound::Trait(trait_bound)
+                if trait_bound.paren_token.is_none()
+                    && trait_bound.lifetimes.is_none()
+                    && trait_bound.path.get_ident().is_some() =>
+            {
+                bounds.push(bound)
+            }
+            TypeParamBound::Lifetime(_) => bounds.push(bound),
+            _ => {
+                let message = "handler bounds must be lifetimes or traits named by identifier";
+                return Err(Error::new_spanned(bound, message));
+            }
+        }
+    }
+    // Handlers are boxed, so they are `'static` unless bounded by a lifetime.
+    if !bounds.iter().any(|bound| matches!(bound, TypeParamBound::Lifetime(_))) {
+        bounds.push(TypeParamBound::Lifetime(Lifetime::new("'static", Span::call_site())));
+    }
+
+    let owner = &item.ident;
+    let events = parse_all(item.items, |method| Event::parse(owner, method))?;
+
+    let attrs = &item.attrs;
+    let vis = &item.vis;
+    let name = &item.ident;
+    let generics = &item.generics;
+    let where_clause = &generics.where_clause;
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    let fields = events.iter().map(|event| {
+        let (attrs, field, name) = (&event.attrs, &event.field, &event.name);
+        quote! {
+            #(#attrs)*
+            #vis #field: #name #ty_generics
+        }
+    });
+    let defaults = events.iter().map(|event| &event.field);
+    // Dispatchers without visibility are public for `event!`.
+    let event_vis = match vis {
+        Visibility::Inherited => quote!(pub(self)),
+        vis => quote!(#vis),
+    };
+    let (flavor, key) = match flavor {
+        Some(Flavor { name, key }) => (Some(name), key),
+        None => (None, None),
+    };
+    let dispatchers = events.iter().map(|event| {
+        let Event { attrs, name, mutable, args, ret, .. } = event;
+        let fn_trait = if *mutable { quote!(FnMut) } else { quote!(Fn) };
+        let args = args.iter().map(|(name, ty)| quote!(#name: #ty));
+        let ret = ret.as_ref().map(|ty| quote!(-> #ty));
+        quote! {
+            ::eventd::event!(
+                #(#attrs)*
+                #event_vis #flavor #name #generics #key #where_clause
+                => #fn_trait(#(#args),*) #ret #(+ #bounds)*
+            );
+        }
+    });
+    Ok(quote! {
+        #(#attrs)*
+        #vis struct #name #generics #where_clause {
+            #(#fields,)*
+        }
+
+        impl #impl_generics ::std::default::Default for #name #ty_generics #where_clause {
+            fn default() -> Self {
+                #name {
+                    #(#defaults: ::std::default::Default::default(),)*
+                }
+            }
+        }
+
+        #(#dispatchers)*
+    })
+}
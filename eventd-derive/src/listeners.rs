@@ -0,0 +1,227 @@
+//! Expansion of `listeners` attribute.
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{Error, FnArg, Ident, ItemTrait, Lifetime, ReturnType, TraitItem, Type, TypeParamBound};
+
+use crate::{check_signature, free_type_param, is_mutable, is_propagation, parse_all};
+
+
+/// Method of listener trait forwarded by dispatcher.
+struct Method {
+    name: Ident,
+    mutable: bool,
+    args: Vec<Type>,
+    output: ReturnType,
+    propagation: bool,
+}
+
+impl Method {
+    fn parse(item: &TraitItem) -> syn::Result<Self> {
+        let method = match item {
+            TraitItem::Fn(method) => method,
+            item => {
+                return Err(Error::new_spanned(item, "listener trait can only declare methods"));
+            }
+        };
+        let sig = &method.sig;
+        check_signature(sig, "listener")?;
+        let mutable = is_mutable(sig, "listener")?;
+        let args = sig.inputs.iter().skip(1).map(|input| match input {
+            FnArg::Typed(arg) => Ok((*arg.ty).clone()),
+            FnArg::Receiver(receiver) => {
+                Err(Error::new_spanned(receiver, "unexpected `self` argument"))
+            }
+        });
+        let propagation = match &sig.output {
+            ReturnType::Default => false,
+            ReturnType::Type(_, ty) => match &**ty {
+                Type::Tuple(tuple) if tuple.elems.is_empty() => false,
+                Type::Path(path) if is_propagation(path) => true,
+                ty => {
+                    return Err(Error::new_spanned(
+                        ty,
+                        "listener methods can only return `()` or `Propagation`",
+                    ));
+                }
+            },
+        };
+        Ok(Method {
+            name: sig.ident.clone(),
+            mutable,
+            args: args.collect::<syn::Result<_>>()?,
+            output: sig.output.clone(),
+            propagation,
+        })
+    }
+}
+
+
+pub(crate) fn expand(item: ItemTrait) -> syn::Result<TokenStream2> {
+    if let Some(token) = item.unsafety {
+        return Err(Error::new_spanned(token, "listener trait can't be `unsafe`"));
+    }
+    if let Some(token) = item.auto_token {
+        return Err(Error::new_spanned(token, "listener trait can't be `auto`"));
+    }
+
+    let methods = parse_all(&item.items, Method::parse)?;
+
+    // Listeners outlive lifetime bounding the trait, like boxed trait objects do by default.
+    let lifetime = item
+        .supertraits
+        .iter()
+        .find_map(|bound| match bound {
+            TypeParamBound::Lifetime(lifetime) => Some(lifetime.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| Lifetime::new("'static", Span::call_site()));
+    let vis = &item.vis;
+    let trait_name = &item.ident;
+    let name = format_ident!("{}Dispatcher", trait_name);
+    let generics = &item.generics;
+    let where_clause = &generics.where_clause;
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    let listener_ty = free_type_param(generics, "L");
+    let bound = quote!(#trait_name #ty_generics + #lifetime);
+    let subscription_methods = subscription_methods(&listener_ty, &bound);
+    let doc = format!(
+        "Dispatcher forwarding calls of [`{}`] methods to all subscribed listeners.",
+        trait_name,
+    );
+
+    // Names used by generated code are hygienic, so they can't clash with argument names.
+    let listener = Ident::new("listener", Span::mixed_site());
+    let visit = Ident::new("visit", Span::mixed_site());
+    let emitted = Ident::new("args", Span::mixed_site());
+    let forwarded = methods.iter().map(|method| {
+        let Method { name, mutable, args, output, propagation } = method;
+        let arg_names: Vec<_> = (0..args.len())
+            .map(|n| Ident::new(&format!("arg{}", n), Span::mixed_site()))
+            .collect();
+        let (receiver, for_each) = if *mutable {
+            (quote!(&mut self), quote!(for_each_mut))
+        } else {
+            (quote!(&self), quote!(for_each))
+        };
+        let call = quote! {
+            let (#(#arg_names,)*) = #emitted.next(#visit);
+            (**#listener).#name(#(#arg_names),*)
+        };
+        let dispatch = if *propagation {
+            quote!(self.handlers.#for_each(|#visit, #listener| { #call }))
+        } else {
+            quote! {
+                self.handlers.#for_each(|#visit, #listener| {
+                    #call;
+                    ::eventd::Propagation::Continue
+                });
+            }
+        };
+        quote! {
+            fn #name(#receiver, #(#arg_names: #args),*) #output {
+                let mut #emitted = ::eventd::Args::new((#(#arg_names,)*));
+                #dispatch
+            }
+        }
+    });
+
+    Ok(quote! {
+        #item
+
+        #[doc = #doc]
+        #vis struct #name #generics #where_clause {
+            handlers: ::eventd::Handlers<Box<dyn #bound>>,
+        }
+
+        impl #impl_generics ::std::default::Default for #name #ty_generics #where_clause {
+            fn default() -> Self {
+                #name {
+                    handlers: ::std::default::Default::default(),
+                }
+            }
+        }
+
+        #[allow(dead_code)]
+        impl #impl_generics #name #ty_generics #where_clause {
+            #subscription_methods
+        }
+
+        impl #impl_generics #trait_name #ty_generics for #name #ty_generics #where_clause {
+            #(#forwarded)*
+        }
+    })
+}
+
+
+/// Generates methods subscribing listener implementing `bound` to dispatcher and unsubscribing
+/// it.
+fn subscription_methods(listener_ty: &Ident, bound: &TokenStream2) -> TokenStream2 {
+    let handler = Ident::new("handler", Span::mixed_site());
+    let priority = Ident::new("priority", Span::mixed_site());
+    let subscription = Ident::new("subscription", Span::mixed_site());
+    quote! {
+        /// Subscribes a listener to be called on event emmision.
+        ///
+        /// Return subscription token.
+        pub fn subscribe<#listener_ty>(
+            &mut self,
+            #handler: #listener_ty,
+        ) -> ::eventd::Subscription<Self>
+        where
+            #listener_ty: #bound,
+        {
+            self.subscribe_with_priority(0, #handler)
+        }
+
+        /// Subscribes a listener to be called on event emmision before handlers with lower
+        /// priority.
+        ///
+        /// Handlers subscribed with `subscribe` have priority `0`. Return subscription token.
+        pub fn subscribe_with_priority<#listener_ty>(
+            &mut self,
+            #priority: i32,
+            #handler: #listener_ty,
+        ) -> ::eventd::Subscription<Self>
+        where
+            #listener_ty: #bound,
+        {
+            self.handlers.insert(#priority, Box::new(#handler))
+        }
+
+        /// Subscribes a listener to be called on event emmision until returned guard is dropped.
+        pub fn subscribe_scoped<#listener_ty>(
+            &mut self,
+            #handler: #listener_ty,
+        ) -> ::eventd::SubscriptionGuard<Self>
+        where
+            #listener_ty: #bound,
+        {
+            let #subscription = self.subscribe(#handler);
+            self.handlers.guard(#subscription)
+        }
+
+        /// Unsubscribes handler for given subscription token.
+        ///
+        /// Returns error if there is no handler for given subscription.
+        pub fn unsubscribe(
+            &mut self,
+            #subscription: ::eventd::Subscription<Self>,
+        ) -> Result<(), ::eventd::Error> {
+            self.unsubscribe_any(#subscription.erase())
+        }
+
+        /// Unsubscribes handler for given type-erased subscription token.
+        ///
+        /// Returns error if there is no handler for given subscription, including the case
+        /// when subscription was issued by another dispatcher.
+        pub fn unsubscribe_any(
+            &mut self,
+            #subscription: ::eventd::AnySubscription,
+        ) -> Result<(), ::eventd::Error> {
+            match self.handlers.remove(&#subscription) {
+                Some(_) => Ok(()),
+                None => Err(::eventd::Error::SubscriptionMissing),
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use eventd::{events, Propagation};
+
+
+mod ui {
+    #[derive(Debug, PartialEq)]
+    pub struct Propagation;
+}
+
+
+mod window {
+    use super::*;
+
+    #[events]
+    pub(crate) trait Events<'a, F: Clone + Debug>: 'a {
+        fn changed(&self, handler: F, args: &F);
+        fn key(&mut self, code: u32) -> Propagation;
+        fn escape(&self) -> ::eventd::Propagation;
+        fn hint(&self) -> ui::Propagation;
+        fn validate(&self, r#type: &str) -> Result<(), String>;
+    }
+}
+
+
+#[events]
+trait Shared: Send + Sync + 'static {
+    fn tick(&self);
+}
+
+
+#[events]
+pub trait Plain {
+    fn tick(&self, x: u32);
+}
+
+
+#[events(Shared)]
+trait Jobs: Send + Sync {
+    fn started(&self, id: u32);
+    fn failed(&mut self, id: u32) -> Result<(), String>;
+}
+
+
+#[events(Keyed[char])]
+trait Keys {
+    fn pressed(&self, shift: bool);
+}
+
+
+#[test]
+fn test_events() {
+    let log = RefCell::new(Vec::new());
+    let mut events = window::Events::<String>::default();
+    let _ = events.changed.subscribe(|old, new| log.borrow_mut().push(format!("{}{}", old, new)));
+    let second = events
+        .changed
+        .subscribe_with_priority(1, |old, _| log.borrow_mut().push(old));
+    events.changed.emit("a".to_string(), &"b".to_string());
+    assert_eq!(*log.borrow(), vec!["a", "ab"]);
+    events.changed.unsubscribe(second).unwrap();
+    events.changed.emit("c".to_string(), &"d".to_string());
+    assert_eq!(log.borrow().len(), 3);
+
+    let _ = events.key.subscribe_with_priority(1, |code| {
+        if code == 27 { Propagation::Stop } else { Propagation::Continue }
+    });
+    let _ = events.key.subscribe(|code| {
+        log.borrow_mut().push(code.to_string());
+        Propagation::Continue
+    });
+    assert!(events.key.emit(27));
+    assert!(!events.key.emit(13));
+    assert_eq!(log.borrow().last().unwrap(), "13");
+
+    // Only `Propagation` of eventd stops propagation, other types are returned as values.
+    let _ = events.escape.subscribe(|| Propagation::Stop);
+    assert!(events.escape.emit());
+    let _ = events.hint.subscribe(|| ui::Propagation);
+    assert_eq!(events.hint.emit_collect(), vec![ui::Propagation]);
+
+    let guard = events.validate.subscribe_scoped(|ty| if ty.is_empty() {
+        Err("empty".to_string())
+    } else {
+        Ok(())
+    });
+    assert_eq!(events.validate.emit_collect(""), vec![Err("empty".to_string())]);
+    drop(guard);
+    assert!(events.validate.emit_collect("").is_empty());
+}
+
+
+#[test]
+fn test_events_shared() {
+    let count = Arc::new(AtomicUsize::new(0));
+    let mut shared = Shared::default();
+    let ticks = count.clone();
+    let subscription = shared.tick.subscribe(move || {
+        ticks.fetch_add(1, Ordering::SeqCst);
+    });
+    std::thread::spawn(move || shared.tick.emit()).join().unwrap();
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+    assert!(Shared::default().tick.unsubscribe_any(subscription.erase()).is_err());
+}
+
+
+#[test]
+fn test_events_plain() {
+    let count = Arc::new(AtomicUsize::new(0));
+    let mut plain = Plain::default();
+    let ticks = count.clone();
+    let _ = plain.tick.subscribe(move |x| {
+        ticks.fetch_add(x as usize, Ordering::SeqCst);
+    });
+    plain.tick.emit(2);
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+}
+
+
+#[test]
+fn test_events_flavors() {
+    let started = Arc::new(AtomicUsize::new(0));
+    let jobs = Arc::new(Jobs::default());
+    let count = started.clone();
+    let _ = jobs.started.subscribe_once(move |id| {
+        count.fetch_add(id as usize, Ordering::SeqCst);
+    });
+    let _ = jobs.failed.subscribe(|id| if id > 1 { Err(id.to_string()) } else { Ok(()) });
+    let remote = jobs.clone();
+    std::thread::spawn(move || remote.started.emit(2)).join().unwrap();
+    jobs.started.emit(3);
+    assert_eq!(started.load(Ordering::SeqCst), 2);
+    assert!(jobs.failed.emit_all(1).is_ok());
+    assert_eq!(jobs.failed.emit_all(2).unwrap_err().errors.len(), 1);
+
+    let pressed = Arc::new(AtomicUsize::new(0));
+    let mut keys = Keys::default();
+    let count = pressed.clone();
+    let _ = keys.pressed.subscribe_key('a', move |shift| {
+        count.fetch_add(if shift { 10 } else { 1 }, Ordering::SeqCst);
+    });
+    keys.pressed.emit(&'a', true);
+    keys.pressed.emit(&'b', false);
+    assert_eq!(pressed.load(Ordering::SeqCst), 10);
+}
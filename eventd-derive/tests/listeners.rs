@@ -0,0 +1,99 @@
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use eventd::{listeners, Propagation};
+
+
+mod window {
+    use super::*;
+
+    #[listeners]
+    pub(crate) trait Listener<'a, T: Clone + Debug + 'a>: 'a {
+        fn changed(&self, value: T, _: &str);
+        fn key(&mut self, code: u32) -> Propagation;
+        fn escape(&self) -> eventd::Propagation;
+    }
+}
+
+
+#[listeners]
+trait Shared: Send + Sync {
+    fn tick(&self);
+}
+
+
+struct Logger<'a> {
+    name: &'static str,
+    log: &'a RefCell<Vec<String>>,
+    consumes: u32,
+    keys: u32,
+}
+
+impl<'a> window::Listener<'a, String> for Logger<'a> {
+    fn changed(&self, value: String, source: &str) {
+        self.log.borrow_mut().push(format!("{} {} {}", self.name, value, source));
+    }
+
+    fn key(&mut self, code: u32) -> Propagation {
+        self.keys += 1;
+        if code == self.consumes { Propagation::Stop } else { Propagation::Continue }
+    }
+
+    fn escape(&self) -> Propagation {
+        Propagation::Continue
+    }
+}
+
+
+struct Counter(Arc<AtomicUsize>);
+
+impl Shared for Counter {
+    fn tick(&self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+
+#[test]
+fn test_listeners() {
+    use window::Listener;
+
+    let log = RefCell::new(Vec::new());
+    let mut dispatcher = window::ListenerDispatcher::<String>::default();
+    let first = dispatcher.subscribe(Logger { name: "first", log: &log, consumes: 27, keys: 0 });
+    let _ = dispatcher
+        .subscribe_with_priority(1, Logger { name: "second", log: &log, consumes: 13, keys: 0 });
+    dispatcher.changed("a".to_string(), "test");
+    assert_eq!(*log.borrow(), vec!["second a test", "first a test"]);
+
+    assert_eq!(dispatcher.key(13), Propagation::Stop);
+    assert_eq!(dispatcher.key(27), Propagation::Stop);
+    assert_eq!(dispatcher.key(0), Propagation::Continue);
+    assert_eq!(dispatcher.escape(), Propagation::Continue);
+
+    dispatcher.unsubscribe(first).unwrap();
+    {
+        let scoped = Logger { name: "scoped", log: &log, consumes: 0, keys: 0 };
+        let _guard = dispatcher.subscribe_scoped(scoped);
+        dispatcher.changed("b".to_string(), "test");
+    }
+    dispatcher.changed("c".to_string(), "test");
+    assert_eq!(log.borrow()[2..], ["second b test", "scoped b test", "second c test"]);
+}
+
+
+#[test]
+fn test_listeners_shared() {
+    let count = Arc::new(AtomicUsize::new(0));
+    let mut dispatcher = SharedDispatcher::default();
+    let _ = dispatcher.subscribe(Counter(count.clone()));
+    let _ = dispatcher.subscribe(Counter(count.clone()));
+
+    let dispatcher = Arc::new(dispatcher);
+    let remote = dispatcher.clone();
+    std::thread::spawn(move || remote.tick()).join().unwrap();
+    dispatcher.tick();
+    assert_eq!(count.load(Ordering::SeqCst), 4);
+}
@@ -0,0 +1,155 @@
+//! Handler storage of dispatchers optimized for frequent emission from multiple threads.
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use arc_swap::ArcSwap;
+
+use crate::handlers::{Entry, Handlers, Visit};
+use crate::filter::FilterCounters;
+use crate::shared::Exclusive;
+use crate::{AnySubscription, FilterStats, Propagation, Subscription, SubscriptionGuard};
+
+
+/// Immutable handlers with copy-on-write list published for emission.
+///
+/// Every subscription change copies handlers into a new immutable list and atomically replaces
+/// published one, so emission only loads the list and never waits for subscription changes.
+/// Handlers detached by guards and once handlers are skipped by emission and dropped from the
+/// list by the next subscription change.
+#[doc(hidden)]
+pub struct Atomic<T> {
+    handlers: Mutex<Handlers<Arc<T>>>,
+    published: ArcSwap<Vec<Entry<Arc<T>>>>,
+}
+
+impl<T> Default for Atomic<T> {
+    fn default() -> Self {
+        Atomic {
+            handlers: Mutex::default(),
+            published: ArcSwap::default(),
+        }
+    }
+}
+
+impl<T> Atomic<T> {
+    pub fn insert<E>(&self, priority: i32, handler: T) -> Subscription<E> {
+        let mut handlers = self.lock();
+        let subscription = handlers.insert(priority, Arc::new(handler));
+        self.publish(&mut handlers);
+        subscription
+    }
+
+    pub fn insert_once<E>(&self, priority: i32, handler: T) -> Subscription<E> {
+        let mut handlers = self.lock();
+        let subscription = handlers.insert_once(priority, Arc::new(handler));
+        self.publish(&mut handlers);
+        subscription
+    }
+
+    pub fn insert_filtered<E>(
+        &self,
+        priority: i32,
+        filter: Arc<FilterCounters>,
+        handler: T,
+    ) -> Subscription<E> {
+        let mut handlers = self.lock();
+        let subscription = handlers.insert_filtered(priority, filter, Arc::new(handler));
+        self.publish(&mut handlers);
+        subscription
+    }
+
+    pub fn guard<E>(&self, subscription: Subscription<E>) -> SubscriptionGuard<E> {
+        self.lock().guard(subscription)
+    }
+
+    pub fn remove(&self, subscription: &AnySubscription) -> Option<Arc<T>> {
+        let mut handlers = self.lock();
+        let handler = handlers.remove(subscription);
+        self.publish(&mut handlers);
+        handler
+    }
+
+    pub fn filter_stats(&self, subscription: &AnySubscription) -> Option<FilterStats> {
+        self.lock().filter_stats(subscription)
+    }
+
+    pub fn for_each(&self, mut f: impl FnMut(&Visit, &T) -> Propagation) -> Propagation {
+        let published = self.published.load();
+        for (position, entry) in published.iter().enumerate() {
+            if entry.is_detached() {
+                continue;
+            }
+            let propagation = f(&entry.visit(position + 1 == published.len()), &entry.handler);
+            entry.called();
+            if propagation == Propagation::Stop {
+                return Propagation::Stop;
+            }
+        }
+        Propagation::Continue
+    }
+
+    fn publish(&self, handlers: &mut Handlers<Arc<T>>) {
+        self.published.store(Arc::new(handlers.snapshot()));
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Handlers<Arc<T>>> {
+        // Only subscription changes hold the lock and they never call handlers, so poisoning can't
+        // leave storage or published list inconsistent.
+        self.handlers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+
+/// Mutable handlers with copy-on-write list published for emission.
+///
+/// Same as `Atomic`, but every handler is locked while it is called, so concurrent emissions
+/// call it one at a time. Emission made by a handler skips handlers which are already running on
+/// its thread.
+#[doc(hidden)]
+pub struct AtomicMut<T> {
+    handlers: Atomic<Exclusive<T>>,
+}
+
+impl<T> Default for AtomicMut<T> {
+    fn default() -> Self {
+        AtomicMut {
+            handlers: Atomic::default(),
+        }
+    }
+}
+
+impl<T> AtomicMut<T> {
+    pub fn insert<E>(&self, priority: i32, handler: T) -> Subscription<E> {
+        self.handlers.insert(priority, Exclusive::new(handler))
+    }
+
+    pub fn insert_once<E>(&self, priority: i32, handler: T) -> Subscription<E> {
+        self.handlers.insert_once(priority, Exclusive::new(handler))
+    }
+
+    pub fn insert_filtered<E>(
+        &self,
+        priority: i32,
+        filter: Arc<FilterCounters>,
+        handler: T,
+    ) -> Subscription<E> {
+        self.handlers.insert_filtered(priority, filter, Exclusive::new(handler))
+    }
+
+    pub fn guard<E>(&self, subscription: Subscription<E>) -> SubscriptionGuard<E> {
+        self.handlers.guard(subscription)
+    }
+
+    pub fn remove(&self, subscription: &AnySubscription) -> Option<Arc<Exclusive<T>>> {
+        self.handlers.remove(subscription)
+    }
+
+    pub fn filter_stats(&self, subscription: &AnySubscription) -> Option<FilterStats> {
+        self.handlers.filter_stats(subscription)
+    }
+
+    pub fn for_each_mut(&self, mut f: impl FnMut(&Visit, &mut T) -> Propagation) -> Propagation {
+        self.handlers.for_each(|visit, handler| {
+            handler.call(|handler| f(visit, handler)).unwrap_or(Propagation::Continue)
+        })
+    }
+}
@@ -0,0 +1,40 @@
+//! Senders of channel-backed subscriptions.
+use std::sync::mpsc::{Sender, SyncSender, TrySendError};
+
+#[cfg(feature = "stream")]
+use crate::stream::StreamSender;
+
+
+/// What channel-backed handler with bounded channel does when the channel is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Block emission until receiver makes room for event.
+    Block,
+    /// Discard event.
+    Drop,
+}
+
+
+/// Sending half of channel used by channel-backed handler.
+#[doc(hidden)]
+pub enum ChannelSender<T> {
+    Unbounded(Sender<T>),
+    Bounded(SyncSender<T>, Backpressure),
+    #[cfg(feature = "stream")]
+    Stream(StreamSender<T>),
+}
+
+impl<T> ChannelSender<T> {
+    /// Sends message to channel. Returns `false` if receiver was dropped.
+    pub fn send(&self, message: T) -> bool {
+        match self {
+            ChannelSender::Unbounded(sender) => sender.send(message).is_ok(),
+            ChannelSender::Bounded(sender, Backpressure::Block) => sender.send(message).is_ok(),
+            ChannelSender::Bounded(sender, Backpressure::Drop) => {
+                !matches!(sender.try_send(message), Err(TrySendError::Disconnected(_)))
+            }
+            #[cfg(feature = "stream")]
+            ChannelSender::Stream(sender) => sender.send(message),
+        }
+    }
+}
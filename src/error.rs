@@ -0,0 +1,58 @@
+//! Errors returned by event dispatchers.
+use std::fmt;
+
+use crate::Subscription;
+
+
+/// Error returned by event dispatchers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Attempt to unsubscribe with invalid subscription, including subscription issued by
+    /// another dispatcher.
+    SubscriptionMissing,
+    /// Attempt to post event to full queue with `Overflow::Error` policy.
+    QueueFull,
+    /// Attempt to subscribe to topic pattern with misplaced wildcard.
+    InvalidTopic,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::SubscriptionMissing => {
+                write!(f, "Attempt to unsubscribe delegate without subscription")
+            }
+            Error::QueueFull => write!(f, "Attempt to post event to full queue"),
+            Error::InvalidTopic => write!(f, "Attempt to subscribe to invalid topic pattern"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+
+/// Errors returned by handlers of dispatcher `D` during single emission, along with
+/// subscriptions of handlers which returned them.
+pub struct HandlerErrors<D, E> {
+    /// Subscriptions of failed handlers and their errors in order of calls.
+    pub errors: Vec<(Subscription<D>, E)>,
+}
+
+impl<D, E: fmt::Debug> fmt::Debug for HandlerErrors<D, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HandlerErrors").field("errors", &self.errors).finish()
+    }
+}
+
+impl<D, E: fmt::Display> fmt::Display for HandlerErrors<D, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} handlers failed", self.errors.len())?;
+        for (i, (_, error)) in self.errors.iter().enumerate() {
+            write!(f, "{} {}", if i == 0 { ":" } else { ";" }, error)?;
+        }
+        Ok(())
+    }
+}
+
+impl<D, E: fmt::Debug + fmt::Display> std::error::Error for HandlerErrors<D, E> {}
@@ -0,0 +1,37 @@
+//! Statistics of filtered subscriptions.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+
+/// Number of events passed to filtered handler and rejected by its predicate.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FilterStats {
+    /// Events accepted by predicate and passed to handler.
+    pub passed: u64,
+    /// Events rejected by predicate.
+    pub rejected: u64,
+}
+
+
+/// Counters shared by filtered handler and storage slot holding it.
+#[doc(hidden)]
+#[derive(Debug, Default)]
+pub struct FilterCounters {
+    passed: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl FilterCounters {
+    /// Counts predicate result and returns it.
+    pub fn record(&self, passed: bool) -> bool {
+        let counter = if passed { &self.passed } else { &self.rejected };
+        counter.fetch_add(1, Ordering::Relaxed);
+        passed
+    }
+
+    pub(crate) fn stats(&self) -> FilterStats {
+        FilterStats {
+            passed: self.passed.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Runtime-agnostic helpers for async handlers.
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+
+/// Future polling all given futures until every one of them completes.
+#[doc(hidden)]
+pub struct JoinAll<F> {
+    pending: Vec<F>,
+}
+
+impl<F> JoinAll<F> {
+    pub fn new(futures: Vec<F>) -> Self {
+        JoinAll { pending: futures }
+    }
+}
+
+impl<F> Future for JoinAll<F>
+where
+    F: Future<Output = ()> + Unpin,
+{
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.pending.retain_mut(|future| Pin::new(future).poll(cx).is_pending());
+        if self.pending.is_empty() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,417 @@
+//! Handler storage shared by all generated event dispatchers.
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+use slab::Slab;
+
+use crate::filter::FilterCounters;
+use crate::{AnySubscription, FilterStats, Propagation, Subscription, SubscriptionGuard};
+
+
+/// Source of unique dispatcher identities.
+static NEXT_OWNER: AtomicU64 = AtomicU64::new(0);
+
+
+struct Slot<T> {
+    generation: u64,
+    priority: i32,
+    once: bool,
+    detached: Arc<Detached>,
+    filter: Option<Arc<FilterCounters>>,
+    handler: T,
+}
+
+
+/// Handler copied out of storage by `snapshot`.
+pub(crate) struct Entry<T> {
+    owner: u64,
+    key: usize,
+    generation: u64,
+    once: bool,
+    detached: Arc<Detached>,
+    pub(crate) handler: T,
+}
+
+impl<T> Entry<T> {
+    pub(crate) fn visit(&self, last: bool) -> Visit<'_> {
+        Visit {
+            owner: self.owner,
+            key: self.key,
+            generation: self.generation,
+            last,
+            detached: &self.detached,
+        }
+    }
+
+    /// Checks whether handler was detached after the copy was taken, so it must not be called.
+    pub(crate) fn is_detached(&self) -> bool {
+        self.detached.is_detached()
+    }
+
+    /// Detaches handler if it should be called only once. Should be called after the handler.
+    pub(crate) fn called(&self) {
+        if self.once {
+            self.detached.detach();
+        }
+    }
+}
+
+
+/// Handler currently visited by storage iteration.
+#[doc(hidden)]
+pub struct Visit<'a> {
+    owner: u64,
+    key: usize,
+    generation: u64,
+    last: bool,
+    detached: &'a Detached,
+}
+
+impl Visit<'_> {
+    /// Checks whether no handlers are left to visit after this one.
+    pub fn is_last(&self) -> bool {
+        self.last
+    }
+
+    /// Returns subscription token of visited handler.
+    pub fn subscription<E>(&self) -> Subscription<E> {
+        Subscription::new(AnySubscription {
+            owner: self.owner,
+            key: self.key,
+            generation: self.generation,
+        })
+    }
+
+    /// Unsubscribes visited handler. It is removed from storage when it is mutably borrowed
+    /// next time.
+    pub fn detach(&self) {
+        self.detached.detach();
+    }
+}
+
+
+/// Takes `FnOnce` handler out of wrapper making it callable through shared reference.
+///
+/// Wrapper is emptied by the first call, so concurrent emissions can't call handler twice.
+#[doc(hidden)]
+pub fn take_once<F>(handler: &Mutex<Option<F>>) -> Option<F> {
+    handler.lock().unwrap_or_else(PoisonError::into_inner).take()
+}
+
+
+/// Arguments of single emission, cloned for every handler but the last one.
+#[doc(hidden)]
+pub struct Args<A> {
+    args: Option<A>,
+}
+
+impl<A: Clone> Args<A> {
+    pub fn new(args: A) -> Self {
+        Args { args: Some(args) }
+    }
+
+    /// Returns arguments for visited handler, moving them out to the last handler.
+    pub fn next(&mut self, visit: &Visit) -> A {
+        let args = if visit.is_last() {
+            self.args.take()
+        } else {
+            self.args.clone()
+        };
+        args.expect("arguments are moved out only to the last handler")
+    }
+}
+
+
+/// Flag of handler which must not be called anymore, shared by its slot, its copies and its
+/// guard.
+///
+/// Guards can be dropped anywhere, including other threads and handlers of the same dispatcher,
+/// so they only set the flag and storage removes handlers when it is mutably borrowed next time.
+/// Until then, handlers and state captured by them stay alive, but are not called.
+#[derive(Debug)]
+pub(crate) struct Detached {
+    detached: AtomicBool,
+    /// Flag of storage telling that some of its handlers were detached.
+    dirty: Arc<AtomicBool>,
+}
+
+impl Detached {
+    pub(crate) fn detach(&self) {
+        self.detached.store(true, Ordering::Release);
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    fn is_detached(&self) -> bool {
+        self.detached.load(Ordering::Acquire)
+    }
+}
+
+
+/// Slab of handlers tagged with generations.
+///
+/// Every storage gets its own identity and every inserted handler gets a fresh generation, so
+/// tokens that outlived their handler or were issued by another dispatcher are rejected even
+/// after the slab reuses their slot.
+///
+/// Slots are visited in dispatch order kept separately from the slab: higher priority first,
+/// then in order of subscription. As generations grow monotonically, they double as subscription
+/// order.
+///
+/// Handlers whose guards were dropped and handlers called once are marked as detached, so
+/// emissions skip them by a single load of their flag, and are removed by the next mutable
+/// borrow of storage.
+#[doc(hidden)]
+pub struct Handlers<T> {
+    owner: u64,
+    next_generation: u64,
+    slots: Slab<Slot<T>>,
+    order: Vec<usize>,
+    dirty: Arc<AtomicBool>,
+}
+
+impl<T> Default for Handlers<T> {
+    fn default() -> Self {
+        Handlers {
+            owner: NEXT_OWNER.fetch_add(1, Ordering::Relaxed),
+            next_generation: 0,
+            slots: Slab::new(),
+            order: Vec::new(),
+            dirty: Arc::default(),
+        }
+    }
+}
+
+impl<T> Handlers<T> {
+    /// Stores handler with given priority and returns subscription token for it.
+    pub fn insert<E>(&mut self, priority: i32, handler: T) -> Subscription<E> {
+        self.insert_slot(priority, false, None, handler)
+    }
+
+    /// Stores handler which is removed after it is called once.
+    pub fn insert_once<E>(&mut self, priority: i32, handler: T) -> Subscription<E> {
+        self.insert_slot(priority, true, None, handler)
+    }
+
+    /// Stores handler which counts events with `filter`, so its statistics can be looked up by
+    /// subscription token.
+    pub fn insert_filtered<E>(
+        &mut self,
+        priority: i32,
+        filter: Arc<FilterCounters>,
+        handler: T,
+    ) -> Subscription<E> {
+        self.insert_slot(priority, false, Some(filter), handler)
+    }
+
+    fn insert_slot<E>(
+        &mut self,
+        priority: i32,
+        once: bool,
+        filter: Option<Arc<FilterCounters>>,
+        handler: T,
+    ) -> Subscription<E> {
+        self.purge();
+        let generation = self.next_generation;
+        self.next_generation += 1;
+        // New handler has the latest generation, so it goes after all handlers of same priority.
+        let slots = &self.slots;
+        let position = self.order.partition_point(|&key| slots[key].priority >= priority);
+        let key = self.slots.insert(Slot {
+            generation,
+            priority,
+            once,
+            detached: Arc::new(Detached {
+                detached: AtomicBool::new(false),
+                dirty: self.dirty.clone(),
+            }),
+            filter,
+            handler,
+        });
+        self.order.insert(position, key);
+        Subscription::new(AnySubscription {
+            owner: self.owner,
+            key,
+            generation,
+        })
+    }
+
+    /// Wraps subscription token issued by this storage into guard.
+    pub fn guard<E>(&self, subscription: Subscription<E>) -> SubscriptionGuard<E> {
+        let detached = match self.slot(subscription.as_any()) {
+            Some(slot) => Arc::downgrade(&slot.detached),
+            None => Weak::new(),
+        };
+        SubscriptionGuard {
+            subscription: Some(subscription),
+            detached,
+        }
+    }
+
+    /// Removes handler for given subscription token.
+    ///
+    /// Returns `None` if token was issued by another storage or its handler is already removed.
+    pub fn remove(&mut self, subscription: &AnySubscription) -> Option<T> {
+        self.purge();
+        if subscription.owner == self.owner {
+            self.remove_slot(subscription.key, subscription.generation)
+        } else {
+            None
+        }
+    }
+
+    /// Returns statistics of filtered handler for given subscription token.
+    ///
+    /// Returns `None` if token is invalid or its handler is not filtered.
+    pub fn filter_stats(&self, subscription: &AnySubscription) -> Option<FilterStats> {
+        let filter = self.slot(subscription)?.filter.as_ref()?;
+        Some(filter.stats())
+    }
+
+    pub(crate) fn owner(&self) -> u64 {
+        self.owner
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Calls `f` for every handler in dispatch order until it stops propagation.
+    pub fn for_each(&self, f: impl FnMut(&Visit, &T) -> Propagation) -> Propagation {
+        self.for_each_followed(false, f)
+    }
+
+    /// Like `for_each`, but if `followed` is set, none of handlers is visited as the last one,
+    /// as more handlers will be visited after them.
+    pub(crate) fn for_each_followed(
+        &self,
+        followed: bool,
+        mut f: impl FnMut(&Visit, &T) -> Propagation,
+    ) -> Propagation {
+        // Handlers can't be removed through shared reference, so detached ones are skipped,
+        // including those detached by handlers called earlier in this emission.
+        for (position, &key) in self.order.iter().enumerate() {
+            let slot = &self.slots[key];
+            if slot.detached.is_detached() {
+                continue;
+            }
+            let visit = Visit {
+                owner: self.owner,
+                key,
+                generation: slot.generation,
+                last: !followed && position + 1 == self.order.len(),
+                detached: &slot.detached,
+            };
+            let propagation = f(&visit, &slot.handler);
+            if slot.once {
+                // Concurrent emissions may still call handler, it is up to handler to run once.
+                slot.detached.detach();
+            }
+            if propagation == Propagation::Stop {
+                return Propagation::Stop;
+            }
+        }
+        Propagation::Continue
+    }
+
+    /// Calls `f` for every handler in dispatch order until it stops propagation, allowing to
+    /// mutate handlers.
+    pub fn for_each_mut(&mut self, f: impl FnMut(&Visit, &mut T) -> Propagation) -> Propagation {
+        self.for_each_mut_followed(false, f)
+    }
+
+    pub(crate) fn for_each_mut_followed(
+        &mut self,
+        followed: bool,
+        mut f: impl FnMut(&Visit, &mut T) -> Propagation,
+    ) -> Propagation {
+        self.purge();
+        for (position, &key) in self.order.iter().enumerate() {
+            let slot = &mut self.slots[key];
+            // Handlers may still drop guards of handlers following them.
+            if slot.detached.is_detached() {
+                continue;
+            }
+            let visit = Visit {
+                owner: self.owner,
+                key,
+                generation: slot.generation,
+                last: !followed && position + 1 == self.order.len(),
+                detached: &slot.detached,
+            };
+            let propagation = f(&visit, &mut slot.handler);
+            if slot.once {
+                slot.detached.detach();
+            }
+            if propagation == Propagation::Stop {
+                return Propagation::Stop;
+            }
+        }
+        Propagation::Continue
+    }
+
+    /// Returns copy of handlers in dispatch order.
+    pub(crate) fn snapshot(&mut self) -> Vec<Entry<T>>
+    where
+        T: Clone,
+    {
+        self.purge();
+        self.order
+            .iter()
+            .map(|&key| {
+                let slot = &self.slots[key];
+                Entry {
+                    owner: self.owner,
+                    key,
+                    generation: slot.generation,
+                    once: slot.once,
+                    detached: slot.detached.clone(),
+                    handler: slot.handler.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Removes handlers whose guards were dropped and handlers called once.
+    pub(crate) fn purge(&mut self) {
+        if self.dirty.swap(false, Ordering::Acquire) {
+            let slots = &mut self.slots;
+            self.order.retain(|&key| {
+                let detached = slots[key].detached.is_detached();
+                if detached {
+                    slots.remove(key);
+                }
+                !detached
+            });
+        }
+    }
+
+    /// Returns slot of handler for given subscription token.
+    fn slot(&self, subscription: &AnySubscription) -> Option<&Slot<T>> {
+        if subscription.owner != self.owner {
+            return None;
+        }
+        self.slots.get(subscription.key).filter(|slot| slot.generation == subscription.generation)
+    }
+
+    fn remove_slot(&mut self, key: usize, generation: u64) -> Option<T> {
+        let rank = match self.slots.get(key) {
+            Some(slot) if slot.generation == generation => Self::rank(slot),
+            _ => return None,
+        };
+        let slots = &self.slots;
+        let position = self
+            .order
+            .binary_search_by(|&other| Self::rank(&slots[other]).cmp(&rank))
+            .expect("stored handler is present in dispatch order");
+        self.order.remove(position);
+        let slot = self.slots.remove(key);
+        // Copies of handler taken by emissions in progress skip it, like ones of dropped guards.
+        slot.detached.detached.store(true, Ordering::Release);
+        Some(slot.handler)
+    }
+
+    fn rank(slot: &Slot<T>) -> (Reverse<i32>, u64) {
+        (Reverse(slot.priority), slot.generation)
+    }
+}
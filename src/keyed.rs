@@ -0,0 +1,134 @@
+//! Handler storage of dispatchers routing events by key.
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::filter::FilterCounters;
+use crate::handlers::{Handlers, Visit};
+use crate::{AnySubscription, FilterStats, Propagation, Subscription, SubscriptionGuard};
+
+
+/// Handlers subscribed to specific keys or to all keys.
+///
+/// Every key gets its own storage, so emission visits only handlers subscribed to emitted key
+/// and handlers subscribed to all keys. Storage of key is dropped once it is found empty, after
+/// unsubscription from it or mutable emission of its key.
+#[doc(hidden)]
+pub struct Keyed<K, T> {
+    all: Handlers<T>,
+    keys: HashMap<K, Handlers<T>>,
+    owners: HashMap<u64, K>,
+}
+
+impl<K, T> Default for Keyed<K, T> {
+    fn default() -> Self {
+        Keyed {
+            all: Handlers::default(),
+            keys: HashMap::new(),
+            owners: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, T> Keyed<K, T> {
+    /// Stores handler subscribed to all keys.
+    pub fn insert<E>(&mut self, priority: i32, handler: T) -> Subscription<E> {
+        self.all.insert(priority, handler)
+    }
+
+    pub fn insert_once<E>(&mut self, priority: i32, handler: T) -> Subscription<E> {
+        self.all.insert_once(priority, handler)
+    }
+
+    pub fn insert_filtered<E>(
+        &mut self,
+        priority: i32,
+        filter: Arc<FilterCounters>,
+        handler: T,
+    ) -> Subscription<E> {
+        self.all.insert_filtered(priority, filter, handler)
+    }
+
+    /// Stores handler subscribed to given key.
+    pub fn insert_key<E>(&mut self, key: K, priority: i32, handler: T) -> Subscription<E> {
+        let owners = &mut self.owners;
+        self.keys
+            .entry(key)
+            .or_insert_with_key(|key| {
+                let handlers = Handlers::default();
+                owners.insert(handlers.owner(), key.clone());
+                handlers
+            })
+            .insert(priority, handler)
+    }
+
+    pub fn guard<E>(&self, subscription: Subscription<E>) -> SubscriptionGuard<E> {
+        match self.handlers(subscription.as_any()) {
+            Some(handlers) => handlers.guard(subscription),
+            None => self.all.guard(subscription),
+        }
+    }
+
+    pub fn remove(&mut self, subscription: &AnySubscription) -> Option<T> {
+        if subscription.owner == self.all.owner() {
+            return self.all.remove(subscription);
+        }
+        let key = self.owners.get(&subscription.owner)?;
+        let handlers = self.keys.get_mut(key)?;
+        let handler = handlers.remove(subscription);
+        if handlers.is_empty() {
+            self.keys.remove(key);
+            self.owners.remove(&subscription.owner);
+        }
+        handler
+    }
+
+    pub fn filter_stats(&self, subscription: &AnySubscription) -> Option<FilterStats> {
+        self.handlers(subscription)?.filter_stats(subscription)
+    }
+
+    /// Calls `f` for every handler subscribed to `key` and then for every handler subscribed to
+    /// all keys, each in dispatch order, until it stops propagation.
+    pub fn for_each(
+        &self,
+        key: &K,
+        mut f: impl FnMut(&Visit, &T) -> Propagation,
+    ) -> Propagation {
+        if let Some(handlers) = self.keys.get(key) {
+            if handlers.for_each_followed(!self.all.is_empty(), &mut f) == Propagation::Stop {
+                return Propagation::Stop;
+            }
+        }
+        self.all.for_each(f)
+    }
+
+    pub fn for_each_mut(
+        &mut self,
+        key: &K,
+        mut f: impl FnMut(&Visit, &mut T) -> Propagation,
+    ) -> Propagation {
+        let followed = !self.all.is_empty();
+        if let Some(handlers) = self.keys.get_mut(key) {
+            let propagation = handlers.for_each_mut_followed(followed, &mut f);
+            // Handlers called once or detached during emission may leave storage empty.
+            handlers.purge();
+            if handlers.is_empty() {
+                self.owners.remove(&handlers.owner());
+                self.keys.remove(key);
+            }
+            if propagation == Propagation::Stop {
+                return Propagation::Stop;
+            }
+        }
+        self.all.for_each_mut(f)
+    }
+
+    /// Returns storage which issued given token.
+    fn handlers(&self, subscription: &AnySubscription) -> Option<&Handlers<T>> {
+        if subscription.owner == self.all.owner() {
+            Some(&self.all)
+        } else {
+            self.keys.get(self.owners.get(&subscription.owner)?)
+        }
+    }
+}
@@ -1,7 +1,7 @@
 //! This crate provides implementation of [observer](https://en.wikipedia.org/wiki/Observer_pattern) design pattern.
 //!
-//! Events are strongly typed, with immediate dispatch and support subscription and unsubscription
-//! of multiple handlers.
+//! Events are strongly typed, with immediate or queued dispatch and support subscription and
+//! unsubscription of multiple handlers.
 //!
 //! Events are defined using `event!` macro. It allows user to fully control lifetime, mutability
 //! and thread safety constraints for event handlers.
@@ -11,11 +11,365 @@
 //! ```ignore
 //! event!(
 //!     /// Optional doc comments
-//!     EventName[<'lifetime>] => [Fn|FnMut]([arg_name: ArgType, ...]) [+ Send + Sync + 'lifetime]
+//!     [pub[(...)]] [Reentrant|Shared|Atomic|Queued|Keyed|Topic] EventName[<'lifetime, ..., T: Bounds, ...>][[KeyType]] [where ...] => [Fn|FnMut|AsyncFn]([arg_name: ArgType, ...]) [-> ReturnType] [+ Send + Sync + 'lifetime] [; [pub[(...)]] emit] [; [pub[(...)]] subscriber SubscriberName]
 //! );
 //! ```
 //!
-//! Event arguments must be `Clone`able types.
+//! Generated dispatcher and its methods have visibility given before event name, or are public
+//! if it is omitted; `pub(self)` makes them private. Visibility of methods emitting events,
+//! including posting and flushing queued events, can be narrowed by visibility followed by `emit`
+//! at the end, so that only owner of dispatcher emits it while anyone can subscribe:
+//!
+//! ```
+//! mod window {
+//!     use eventd::event;
+//!
+//!     event!(pub(crate) Closed => Fn(code: u32) + 'static; emit);
+//!
+//!     #[derive(Default)]
+//!     pub struct Window {
+//!         pub closed: Closed,
+//!     }
+//!
+//!     impl Window {
+//!         pub fn close(&self) {
+//!             self.closed.emit(0);
+//!         }
+//!     }
+//! }
+//!
+//! let mut window = window::Window::default();
+//! let _ = window.closed.subscribe(|code| println!("Closed with {}", code));
+//! window.close();
+//! ```
+//!
+//! ```compile_fail
+//! mod window {
+//!     eventd::event!(pub(crate) Closed => Fn(code: u32) + 'static; emit);
+//! }
+//!
+//! window::Closed::default().emit(0);
+//! ```
+//!
+//! Subscribing methods can be also split off to separate type named after `subscriber` at the end.
+//! Subscriber stores handlers and is reachable from dispatcher through `Deref`, so component can
+//! hand out reference to subscriber of its event while keeping dispatcher itself private.
+//! Subscriber has visibility of dispatcher unless visibility is given before `subscriber`, and
+//! can't be less visible than dispatcher:
+//!
+//! ```
+//! mod window {
+//!     use eventd::event;
+//!
+//!     event!(
+//!         pub(crate) Shared Resized => Fn(width: u32, height: u32) + 'static;
+//!         pub subscriber OnResized
+//!     );
+//!
+//!     #[derive(Default)]
+//!     pub struct Window {
+//!         resized: Resized,
+//!     }
+//!
+//!     impl Window {
+//!         pub fn on_resized(&self) -> &OnResized {
+//!             &self.resized
+//!         }
+//!
+//!         pub fn resize(&self, width: u32, height: u32) {
+//!             self.resized.emit(width, height);
+//!         }
+//!     }
+//! }
+//!
+//! let window = window::Window::default();
+//! let subscription = window.on_resized().subscribe(|w, h| println!("Resized to {}x{}", w, h));
+//! window.resize(640, 480);
+//! window.on_resized().unsubscribe(subscription).unwrap();
+//! ```
+//!
+//! Subscriber can't be created on its own, so holder of `&mut` subscriber can't wipe handlers
+//! by replacing it with an empty one. It can still swap it with subscriber of another dispatcher
+//! of the same event, so subscriber of dispatcher taking `&self`, like `Shared` one, is safer to
+//! hand out as shared reference.
+//!
+//! ```compile_fail
+//! eventd::event!(Resized => Fn(width: u32, height: u32) + 'static; subscriber OnResized);
+//!
+//! let mut resized = Resized::default();
+//! let _ = std::mem::take(&mut *resized);
+//! ```
+//!
+//! Lifetime and type parameters with their bounds and where clause are declared on generated
+//! dispatcher, so one definition serves events with different argument types:
+//!
+//! ```
+//! use eventd::event;
+//!
+//! event!(Changed<T: Clone> where T: PartialEq => Fn(old: T, new: T) + 'static);
+//!
+//! let mut count = Changed::<u32>::default();
+//! let mut name = Changed::<String>::default();
+//! let _ = count.subscribe(|old, new| assert!(old < new));
+//! let _ = name.subscribe(|old, new| assert_ne!(old, new));
+//! count.emit(1, 2);
+//! name.emit("old".into(), "new".into());
+//! ```
+//!
+//! Event arguments must be `Clone`able types. Emission clones them for every handler but the
+//! last one. Arguments declared as references, like `Fn(payload: &Payload)`, are passed to handlers
+//! by reference and don't require referenced type to be `Clone`.
+//!
+//! Handlers subscribed with `subscribe_once` may be `FnOnce` closures: they are unsubscribed after
+//! the first call. For events declared with return type other than `Propagation` such handlers
+//! are not supported.
+//!
+//! Handlers of events declared with `-> Propagation` decide whether event is passed to handlers
+//! with lower priority, see `Propagation`.
+//!
+//! Values returned by handlers of events declared with other return type can be collected with
+//! `emit_collect` or reduced with `emit_fold`, `emit_any` and `emit_all_ok`. Failures of
+//! handlers returning `Result` can be also gathered with `emit_all`, which calls all handlers and
+//! reports which of them failed:
+//!
+//! ```
+//! use eventd::event;
+//!
+//! event!(Validate => Fn(name: &str) -> Result<(), String> + 'static);
+//!
+//! let mut validate = Validate::default();
+//! let _ = validate.subscribe(|name| if name.is_empty() { Err("empty".into()) } else { Ok(()) });
+//! let _ = validate.subscribe(|name| if name.len() > 4 { Err("long".into()) } else { Ok(()) });
+//! assert_eq!(validate.emit_all_ok("John"), Ok(()));
+//! assert_eq!(validate.emit_all_ok("Johnny"), Err("long".to_string()));
+//! assert_eq!(validate.emit_collect("").len(), 2);
+//! assert_eq!(validate.emit_all("").unwrap_err().errors.len(), 1);
+//! ```
+//!
+//! Handlers subscribed with `subscribe_filtered` are called only for events accepted by given
+//! predicate. Numbers of accepted and rejected events are available from `filter_stats`:
+//!
+//! ```
+//! use eventd::{event, FilterStats};
+//!
+//! event!(Key => Fn(code: u32) + 'static);
+//!
+//! let mut key = Key::default();
+//! let escape = key.subscribe_filtered(|&code| code == 27, |_| println!("Escape pressed"));
+//! key.emit(13);
+//! key.emit(27);
+//! assert_eq!(key.filter_stats(&escape), Some(FilterStats { passed: 1, rejected: 1 }));
+//! ```
+//!
+//! Panic of handler called by `emit` unwinds through it, so the rest of handlers are not called.
+//! `emit_catching` calls every handler under `catch_unwind` instead and returns caught panics
+//! along with subscriptions of handlers which panicked, optionally unsubscribing them.
+//!
+//! # Keyed events
+//!
+//! Dispatchers declared with `Keyed` route events by key of given type: handlers subscribed with
+//! `subscribe_key` are called only for events emitted with their key, handlers subscribed with
+//! `subscribe` are called for all keys after handlers of the key. Emission visits only handlers
+//! it calls, no matter how many keys have handlers.
+//!
+//! ```
+//! use std::cell::RefCell;
+//! use eventd::event;
+//!
+//! event!(Keyed Moved<'a>[u32] => Fn(x: i32, y: i32) + 'a);
+//!
+//! let log = RefCell::new(Vec::new());
+//! let mut moved = Moved::default();
+//! let _ = moved.subscribe_key(1, |x, y| log.borrow_mut().push(format!("1 to {}, {}", x, y)));
+//! let _ = moved.subscribe(|_, _| log.borrow_mut().push("any".to_string()));
+//! moved.emit(&1, 2, 3);
+//! moved.emit(&2, 2, 3);
+//! assert_eq!(*log.borrow(), vec!["1 to 2, 3", "any", "any"]);
+//! ```
+//!
+//! Dispatchers declared with `Topic` route events by `/`-separated topics, like `sensors/1/temp`.
+//! Handlers are subscribed with `subscribe_topic` to MQTT-style patterns, where `+` level
+//! matches any single level and `#` as the last level matches any number of remaining levels,
+//! including none. Handlers of all patterns matching emitted topic are called, with patterns
+//! preferring literal levels to `+` and `+` to `#` going first.
+//!
+//! ```
+//! use std::cell::RefCell;
+//! use eventd::event;
+//!
+//! event!(Topic Reading<'a> => Fn(value: f64) + 'a);
+//!
+//! let log = RefCell::new(Vec::new());
+//! let mut reading = Reading::default();
+//! let _ = reading.subscribe_topic("sensors/+/temp", |v| log.borrow_mut().push(("temp", v)));
+//! let _ = reading.subscribe_topic("sensors/#", |v| log.borrow_mut().push(("sensors", v)));
+//! reading.emit("sensors/1/temp", 20.5);
+//! reading.emit("sensors/1/humidity", 0.4);
+//! assert_eq!(*log.borrow(), vec![("temp", 20.5), ("sensors", 20.5), ("sensors", 0.4)]);
+//! assert!(reading.subscribe_topic("sensors/#/temp", |_| {}).is_err());
+//! ```
+//!
+//! # Async handlers
+//!
+//! Handlers of events declared with `AsyncFn` return boxed futures, which are awaited by
+//! `emit_async` one after another or by `emit_async_concurrent` all at once. Dispatchers don't
+//! depend on any async runtime. Futures must be `Send` if handlers are declared `Send`, and must
+//! outlive handler lifetime bound.
+//!
+//! ```
+//! use eventd::event;
+//!
+//! event!(Saved => AsyncFn(id: u32) + Send + Sync + 'static);
+//!
+//! async fn store(id: u32) {
+//!     // ...
+//! }
+//!
+//! async fn save(saved: &Saved) {
+//!     saved.emit_async(42).await;
+//! }
+//!
+//! let mut saved = Saved::default();
+//! let _ = saved.subscribe(|id| Box::pin(store(id)));
+//! let _ = save(&saved);
+//! ```
+//!
+//! # Queued emission
+//!
+//! Dispatchers declared with `Queued` can also defer events: `post` queues event arguments and
+//! `flush` dispatches queued events later in order they were posted. `take_queued` removes
+//! queued events without dispatching them. Queue is unbounded by default, dispatcher created
+//! with `with_capacity` handles posts to full queue according to given `Overflow` policy.
+//! Arguments of queued events can't borrow with elided lifetimes, and `AsyncFn` handlers are not
+//! supported.
+//!
+//! ```compile_fail
+//! eventd::event!(Queued Saved => AsyncFn(id: u32) + 'static);
+//! ```
+//!
+//! ```
+//! use eventd::{event, Overflow};
+//!
+//! event!(Queued Input => FnMut(key: char) + 'static);
+//!
+//! let mut input = Input::with_capacity(2, Overflow::DropOldest);
+//! input.post('a').unwrap();
+//! input.post('b').unwrap();
+//! input.post('c').unwrap();
+//! assert_eq!(input.queued(), 2);
+//!
+//! let _ = input.subscribe(|key| print!("{}", key));
+//! assert_eq!(input.flush(), 2);
+//! ```
+//!
+//! # Re-entrancy
+//!
+//! Dispatchers declared with `Reentrant` take `&self` in all methods and can be used by their
+//! own handlers: handlers may subscribe and unsubscribe handlers and emit the event again.
+//!
+//! Emission calls handlers which were subscribed when it started: handlers subscribed during
+//! emission are not called for the current event. Handlers unsubscribed during emission, either
+//! by `unsubscribe` or by dropping their guards, are not called by it anymore if their turn
+//! hasn't come yet. `FnMut` handler is not called by nested emission while it runs in outer one.
+//!
+//! ```
+//! use std::cell::Cell;
+//! use std::rc::Rc;
+//! use eventd::event;
+//!
+//! event!(Reentrant Frame => Fn(n: u32) + 'static);
+//!
+//! let frame = Rc::new(Frame::default());
+//! let seen = Rc::new(Cell::new(0));
+//! let subscription = Rc::new(Cell::new(None));
+//!
+//! // One-shot handler removing itself.
+//! let (weak, token, counter) = (Rc::downgrade(&frame), subscription.clone(), seen.clone());
+//! subscription.set(Some(frame.subscribe(move |n| {
+//!     counter.set(counter.get() + n);
+//!     if let (Some(frame), Some(token)) = (weak.upgrade(), token.take()) {
+//!         frame.unsubscribe(token).unwrap();
+//!     }
+//! })));
+//!
+//! frame.emit(1);
+//! frame.emit(2);
+//! assert_eq!(seen.get(), 1);
+//! ```
+//!
+//! # Sharing between threads
+//!
+//! Dispatchers declared with `Shared` take `&self` in all methods and lock their handlers
+//! internally, so with `Send + Sync` handlers they can be subscribed to and emitted from multiple
+//! threads at once. Lock is held only while handlers are added, removed or copied: emission calls
+//! handlers which were subscribed when it started without holding it, so handlers may use the
+//! dispatcher too. `FnMut` handlers are called by one emission at a time: emission waits for
+//! `FnMut` handlers running on other threads, and skips ones running on its own thread, like
+//! `Reentrant` dispatchers do. Since running `FnMut` handler is locked, emission made by it can
+//! deadlock with emission of another thread made by `FnMut` handler it waits for, if each waits
+//! for handler the other one runs.
+//!
+//! ```
+//! use std::sync::Arc;
+//! use std::sync::atomic::{AtomicU32, Ordering};
+//! use std::thread;
+//! use eventd::event;
+//!
+//! event!(Shared Tick => Fn(n: u32) + Send + Sync + 'static);
+//!
+//! let tick = Arc::new(Tick::default());
+//! let total = Arc::new(AtomicU32::new(0));
+//! let counter = total.clone();
+//! let _ = tick.subscribe(move |n| { counter.fetch_add(n, Ordering::Relaxed); });
+//!
+//! let threads: Vec<_> = (0..4)
+//!     .map(|_| {
+//!         let tick = tick.clone();
+//!         thread::spawn(move || tick.emit(1))
+//!     })
+//!     .collect();
+//! for thread in threads {
+//!     thread.join().unwrap();
+//! }
+//! assert_eq!(total.load(Ordering::Relaxed), 4);
+//! ```
+//!
+//! Dispatchers declared with `Atomic` have the same interface, but are tuned for events which are
+//! emitted often and rarely subscribed to. Every subscription change copies list of handlers and
+//! atomically publishes it, while emission just loads published list without taking locks.
+//!
+//! Events can also be passed to other threads through channels: `subscribe_channel` subscribes a
+//! handler sending tuples of event arguments to returned receiver, `subscribe_sync_channel` does
+//! the same with bounded channel. Handler is unsubscribed once receiver is dropped. Channels are
+//! supported for events without return type or declared with `-> Propagation`.
+//!
+//! ```
+//! use std::thread;
+//! use eventd::event;
+//!
+//! event!(Moved => Fn(x: i32, y: i32) + 'static);
+//!
+//! let mut moved = Moved::default();
+//! let receiver = moved.subscribe_channel();
+//! let worker = thread::spawn(move || receiver.iter().map(|(x, y)| x + y).sum::<i32>());
+//! moved.emit(1, 2);
+//! moved.emit(3, 4);
+//! drop(moved);
+//! assert_eq!(worker.join().unwrap(), 10);
+//! ```
+//!
+//! With `stream` feature enabled, `subscribe_stream` returns `EventStream` of event arguments for
+//! async consumers. Stream buffers limited number of events and yields `Lagged` error when it had
+//! to discard some of them because consumer was too slow.
+//!
+//! # Events traits
+//!
+//! With `derive` feature enabled, `events` attribute defines dispatchers of several events at once
+//! from methods of a trait. Dispatchers are defined with `event!` and get generic parameters,
+//! where clause and visibility of the trait, see `events` for details.
+//!
+//! `listeners` attribute defines dispatcher implementing a trait by forwarding calls of its
+//! methods to all subscribed listener objects implementing the same trait, see `listeners`.
 //!
 //! # Examples
 //!
@@ -46,33 +400,90 @@
 //!     my_event.emit(42);
 //! }
 //! ```
-use std::fmt;
+mod atomic;
+mod channel;
+mod error;
+mod filter;
+mod future;
+mod handlers;
+mod keyed;
+mod panic;
+mod queue;
+mod reentrant;
+mod shared;
+#[cfg(feature = "stream")]
+mod stream;
+mod subscription;
+mod topics;
 
 #[doc(hidden)]
-pub use slab::Slab;
+pub use crate::atomic::{Atomic, AtomicMut};
+pub use crate::channel::Backpressure;
+#[doc(hidden)]
+pub use crate::channel::ChannelSender;
+pub use crate::error::{Error, HandlerErrors};
+pub use crate::filter::FilterStats;
+#[doc(hidden)]
+pub use crate::filter::FilterCounters;
+#[doc(hidden)]
+pub use crate::future::JoinAll;
+#[doc(hidden)]
+pub use crate::handlers::{take_once, Args, Handlers, Visit};
+#[doc(hidden)]
+pub use crate::keyed::Keyed;
+#[doc(hidden)]
+pub use crate::panic::catch_panic;
+pub use crate::panic::{HandlerPanic, OnPanic};
+#[doc(hidden)]
+pub use crate::queue::Queue;
+pub use crate::queue::Overflow;
+#[doc(hidden)]
+pub use crate::reentrant::Reentrant;
+#[doc(hidden)]
+pub use crate::shared::{Shared, SharedMut};
+#[cfg(feature = "stream")]
+pub use crate::stream::{EventStream, Lagged};
+#[cfg(feature = "stream")]
+#[doc(hidden)]
+pub use crate::stream::StreamSender;
+pub use crate::subscription::{
+    AnySubscription, Subscription, SubscriptionGuard,
+};
+#[doc(hidden)]
+pub use crate::topics::Topics;
+#[cfg(feature = "derive")]
+pub use eventd_derive::{events, listeners};
 
 
-/// Token used to differentiate subscribers.
+/// Value returned by handlers of events declared with `-> Propagation`.
 ///
-/// Should be passed back to event dispatcher to unsubscribe.
-pub struct Subscription {
-    #[doc(hidden)]
-    pub key: usize,
-}
-
-
-/// Error emmited on attempt to unsubscribe with invalid subscription.
-#[derive(Debug)]
-pub struct SubscriptionMissing;
-
-impl fmt::Display for SubscriptionMissing {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Attempt to unsubscribe delegate without subscription")
-    }
+/// Handler returning `Stop` consumes the event: handlers after it are not called and `emit`
+/// reports event as consumed.
+///
+/// ```
+/// use eventd::{event, Propagation};
+///
+/// event!(KeyPressed => Fn(key: char) -> Propagation + 'static);
+///
+/// let mut key_pressed = KeyPressed::default();
+/// let _ = key_pressed.subscribe_with_priority(1, |key| {
+///     if key == 'q' { Propagation::Stop } else { Propagation::Continue }
+/// });
+/// let _ = key_pressed.subscribe(|key| {
+///     assert_ne!(key, 'q');
+///     Propagation::Continue
+/// });
+/// assert!(key_pressed.emit('q'));
+/// assert!(!key_pressed.emit('w'));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagation {
+    /// Pass event to the next handler.
+    Continue,
+    /// Consume event.
+    Stop,
 }
 
-impl std::error::Error for SubscriptionMissing {}
-
 
 /// Macro used for defining event dispatcher types.
 ///
@@ -81,165 +492,2529 @@ impl std::error::Error for SubscriptionMissing {}
 macro_rules! event {
     (
         $(#[$attr:meta])*
-        $name:ident
-        $(< $lt:lifetime >)? => Fn($($arg_name:ident : $arg_ty:ty),*)
-        $(+ $bound:tt)*
-    ) => {
-        __event_impl!(
-            $(#[$attr])*,
-            $name$(< $lt >)? => Fn,
-            [$($arg_name: $arg_ty),*],
-            [$($bound),*],
-            self: &Self, &self.handlers
-        );
+        pub($($restriction:tt)+) $name:ident $($rest:tt)+
+    ) => {
+        __event_flavor!([$(#[$attr])*], [pub($($restriction)+)], $name $($rest)+);
     };
     (
         $(#[$attr:meta])*
-        $name:ident
-        $(< $lt:lifetime >)? => FnMut($($arg_name:ident : $arg_ty:ty),*)
-        $(+ $bound:tt)*
-    ) => {
-        __event_impl!(
-            $(#[$attr])*,
-            $name$(< $lt >)? => FnMut,
-            [$($arg_name: $arg_ty),*],
-            [$($bound),*],
-            self: &mut Self, &mut self.handlers
-        );
+        pub $name:ident $($rest:tt)+
+    ) => {
+        __event_flavor!([$(#[$attr])*], [pub], $name $($rest)+);
+    };
+    (
+        $(#[$attr:meta])*
+        $name:ident $($rest:tt)+
+    ) => {
+        __event_flavor!([$(#[$attr])*], [pub], $name $($rest)+);
     };
 }
 
 
+/// Picks dispatcher flavor from prefix of event name.
 #[doc(hidden)]
 #[macro_export]
-macro_rules! __event_impl {
+macro_rules! __event_flavor {
     (
-        $(#[$attr:meta])*,
-        $name:ident $(< $lt:lifetime >)? => $fn:tt,
-        [$($arg_name:ident: $arg_ty:ty),*],
-        [$($bound:tt),*],
-        $self:ident: $self_ty:ty, $iter_ex:expr
+        [$($attr:tt)*], [$($vis:tt)*],
+        Reentrant $name:ident $($rest:tt)+
     ) => {
-        $(#[$attr])*
-        pub struct $name$(<$lt>)? {
-            handlers: $crate::Slab<Box<$fn($($arg_ty),*) $( + $bound)*>>,
-        }
-
-        impl$(<$lt>)? Default for $name$(<$lt>)? {
-            fn default() -> Self {
-                $name {
-                    handlers: $crate::Slab::new(),
-                }
-            }
-        }
-
-        #[allow(dead_code)]
-        impl$(<$lt>)? $name$(<$lt>)?  {
-            /// Subscribes a closure to be called on event emmision.
-            ///
-            /// Return subscription token.
-            pub fn subscribe<F>(&mut self, handler: F) -> $crate::Subscription
-            where
-                F: $fn($($arg_ty),*) $( + $bound)*,
-            {
-                $crate::Subscription {
-                    key: self.handlers.insert(Box::new(handler)),
-                }
-            }
-
-            /// Unsubscribes handler for given subscription token.
-            ///
-            /// Returns error if there is no handler for given subscription.
-            pub fn unsubscribe(
-                &mut self,
-                subscription: $crate::Subscription,
-            ) -> Result<(), $crate::SubscriptionMissing> {
-                if self.handlers.contains(subscription.key) {
-                    self.handlers.remove(subscription.key);
-                    Ok(())
-                } else {
-                    Err($crate::SubscriptionMissing)
-                }
-            }
-
-            /// Dispatches a call with given arguments to all subscribed handlers.
-            ///
-            /// Arguments must be clonable.
-            pub fn emit($self:$self_ty, $($arg_name: $arg_ty),*) {
-                for (_, handler) in $iter_ex {
-                    (*handler)($($arg_name.clone()),*)
-                }
-            }
-        }
+        $crate::__event_generics!(
+            [[$($attr)*], [$($vis)*], $name], plain, [[], [Reentrant, &Self], [Reentrant, &Self]],
+            $($rest)+
+        );
+    };
+    (
+        [$($attr:tt)*], [$($vis:tt)*],
+        Shared $name:ident $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            [[$($attr)*], [$($vis)*], $name], plain, [[], [Shared, &Self], [SharedMut, &Self]],
+            $($rest)+
+        );
+    };
+    (
+        [$($attr:tt)*], [$($vis:tt)*],
+        Atomic $name:ident $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            [[$($attr)*], [$($vis)*], $name], plain, [[], [Atomic, &Self], [AtomicMut, &Self]],
+            $($rest)+
+        );
+    };
+    (
+        [$($attr:tt)*], [$($vis:tt)*],
+        Queued $name:ident $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            [[$($attr)*], [$($vis)*], $name], queued,
+            [[mut], [Handlers, &Self], [Handlers, &mut Self]],
+            $($rest)+
+        );
+    };
+    (
+        [$($attr:tt)*], [$($vis:tt)*],
+        Keyed $name:ident $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            [[$($attr)*], [$($vis)*], $name], keyed, [[mut], [Keyed, &Self], [Keyed, &mut Self]],
+            $($rest)+
+        );
+    };
+    (
+        [$($attr:tt)*], [$($vis:tt)*],
+        Topic $name:ident $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            [[$($attr)*], [$($vis)*], $name], topic, [[mut], [Topics, &Self], [Topics, &mut Self]],
+            $($rest)+
+        );
+    };
+    (
+        [$($attr:tt)*], [$($vis:tt)*],
+        $name:ident $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            [[$($attr)*], [$($vis)*], $name], plain,
+            [[mut], [Handlers, &Self], [Handlers, &mut Self]],
+            $($rest)+
+        );
     };
 }
 
-pub mod example {
-    //! Module with sample event.
-    //!
-    //! Generated using:
-    //!
-    //! ```
-    //! use eventd::event;
-    //!
-    //! event!(
-    //!     /// Sample event generated using `event!` macro.
-    //!     ExampleEvent<'a> => Fn(x: u32, y: &str) + Sync + 'a
-    //! );
-    //! ```
-    event!(
-        /// Sample event generated using `event!` macro.
-        ExampleEvent<'a> => Fn(x: u32, y: &str) + Sync + 'a
-    );
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_event() {
-        event!(MyEvent<'a> => FnMut(x: u8, borrowed: &str) + 'a);
-
-        let mut called1 = false;
-        let mut called2 = false;
-        let mut some_buffer = Vec::new();
-        {
-            let mut my_event = MyEvent::default();
-            my_event.subscribe(|x, y| {
-                called1 = true;
-                assert_eq!(x, 42);
-                assert_eq!(y, "foo");
-            });
-            my_event.subscribe(|x, _| {
-                called2 = true;
-                some_buffer.push(x);
-            });
-
-            my_event.emit(42, "foo");
-        }
-
-        assert!(called1);
-        assert!(called2);
-        assert_eq!(some_buffer, vec![42]);
-    }
-
-    #[test]
-    fn test_unsubscribe() {
-        event!(MyEvent<'a> => FnMut() + 'a);
 
-        let mut called = 0u8;
-        {
-            let mut my_event = MyEvent::default();
-            let subscription = my_event.subscribe(|| {
-                called += 1;
-            });
+/// Parses generic parameters, key type, where clause and clauses setting visibility of emitting
+/// methods and subscriber following event name.
+///
+/// Generic parameters are split at commas outside of angle brackets, so their bounds may have
+/// generic arguments. Each parameter is passed on with its bounds for `impl` and without them for
+/// type.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_generics {
+    ([$($head:tt)*], $kind:ident, [$($state:tt)*], < $($rest:tt)+) => {
+        $crate::__event_generics!(
+            @param [$($head)*], $kind, [$($state)*], [] [] [] [], $($rest)+
+        );
+    };
+    ([$($head:tt)*], $kind:ident, [$($state:tt)*], $($rest:tt)+) => {
+        $crate::__event_generics!(@key [$($head)*], $kind, [$($state)*], [] [], $($rest)+);
+    };
+    (
+        @param [$($head:tt)*], $kind:ident, [$($state:tt)*],
+        [$($impl:tt)*] [$($ty:tt)*] [] [], > $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            @key [$($head)*], $kind, [$($state)*], [$($impl)*] [$($ty)*], $($rest)+
+        );
+    };
+    (
+        @param [$($head:tt)*], $kind:ident, [$($state:tt)*],
+        [$($impl:tt)*] [$($ty:tt)*] [$first:tt $($param:tt)*] [], > $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            @key [$($head)*], $kind, [$($state)*],
+            [$($impl)* $first $($param)*,] [$($ty)* $first,], $($rest)+
+        );
+    };
+    (
+        @param [$($head:tt)*], $kind:ident, [$($state:tt)*],
+        [$($impl:tt)*] [$($ty:tt)*] [$first:tt $($param:tt)*] [], , $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            @param [$($head)*], $kind, [$($state)*],
+            [$($impl)* $first $($param)*,] [$($ty)* $first,] [] [], $($rest)+
+        );
+    };
+    (
+        @param [$($head:tt)*], $kind:ident, [$($state:tt)*],
+        [$($impl:tt)*] [$($ty:tt)*] [$($param:tt)*] [$($depth:tt)*], < $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            @param [$($head)*], $kind, [$($state)*],
+            [$($impl)*] [$($ty)*] [$($param)* <] [@ $($depth)*], $($rest)+
+        );
+    };
+    (
+        @param [$($head:tt)*], $kind:ident, [$($state:tt)*],
+        [$($impl:tt)*] [$($ty:tt)*] [$($param:tt)*] [@ $($depth:tt)*], > $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            @param [$($head)*], $kind, [$($state)*],
+            [$($impl)*] [$($ty)*] [$($param)* >] [$($depth)*], $($rest)+
+        );
+    };
+    (
+        @param [$($head:tt)*], $kind:ident, [$($state:tt)*],
+        [$($impl:tt)*] [$($ty:tt)*] [$($param:tt)*] [@ @ $($depth:tt)*], >> $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            @param [$($head)*], $kind, [$($state)*],
+            [$($impl)*] [$($ty)*] [$($param)* >>] [$($depth)*], $($rest)+
+        );
+    };
+    // `>>` closing the last bracket of parameter also closes parameter list.
+    (
+        @param [$($head:tt)*], $kind:ident, [$($state:tt)*],
+        [$($impl:tt)*] [$($ty:tt)*] [$($param:tt)*] [@], >> $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            @param [$($head)*], $kind, [$($state)*],
+            [$($impl)*] [$($ty)*] [$($param)* >] [], > $($rest)+
+        );
+    };
+    // Common shapes of parameters and bounds are taken at once to keep recursion shallow.
+    (
+        @param [$($head:tt)*], $kind:ident, [$($state:tt)*],
+        [$($impl:tt)*] [$($ty:tt)*] [$($param:tt)*] [$($depth:tt)*],
+        $name:ident : $bound:ident $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            @param [$($head)*], $kind, [$($state)*],
+            [$($impl)*] [$($ty)*] [$($param)* $name: $bound] [$($depth)*], $($rest)+
+        );
+    };
+    (
+        @param [$($head:tt)*], $kind:ident, [$($state:tt)*],
+        [$($impl:tt)*] [$($ty:tt)*] [$($param:tt)*] [$($depth:tt)*],
+        + $bound:ident $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            @param [$($head)*], $kind, [$($state)*],
+            [$($impl)*] [$($ty)*] [$($param)* + $bound] [$($depth)*], $($rest)+
+        );
+    };
+    (
+        @param [$($head:tt)*], $kind:ident, [$($state:tt)*],
+        [$($impl:tt)*] [$($ty:tt)*] [$($param:tt)*] [$($depth:tt)*], $next:tt $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            @param [$($head)*], $kind, [$($state)*],
+            [$($impl)*] [$($ty)*] [$($param)* $next] [$($depth)*], $($rest)+
+        );
+    };
+    (@key [$($head:tt)*], keyed, [$($state:tt)*], $impl:tt $ty:tt, [$key:ty] $($rest:tt)+) => {
+        $crate::__event_generics!(
+            @where [$($head)*], [keyed [$key] [$key]], [$($state)*], $impl $ty, [], $($rest)+
+        );
+    };
+    (@key [$($head:tt)*], topic, [$($state:tt)*], $impl:tt $ty:tt, $($rest:tt)+) => {
+        $crate::__event_generics!(
+            @where [$($head)*], [topic [str] []], [$($state)*], $impl $ty, [], $($rest)+
+        );
+    };
+    (@key [$($head:tt)*], $kind:ident, [$($state:tt)*], $impl:tt $ty:tt, $($rest:tt)+) => {
+        $crate::__event_generics!(
+            @where [$($head)*], [$kind [] []], [$($state)*], $impl $ty, [], $($rest)+
+        );
+    };
+    (
+        @where [$($head:tt)*], [$($kind:tt)*], [$($state:tt)*], [$($impl:tt)*] [$($ty:tt)*],
+        [$($where:tt)*], => $fn:ident ($($args:tt)*) $($rest:tt)*
+    ) => {
+        $crate::__event_returning!(
+            [$($head)* [$($impl)*] [$($ty)*] [$($where)*], $($kind)*], [$($state)*],
+            [$fn($($args)*)], $($rest)*
+        );
+    };
+    (
+        @where [$($head:tt)*], [$($kind:tt)*], [$($state:tt)*], $impl:tt $ty:tt,
+        [], where $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            @where [$($head)*], [$($kind)*], [$($state)*], $impl $ty, [where], $($rest)+
+        );
+    };
+    // Where clause is taken by several tokens at once, as long as they don't reach `=>`.
+    (
+        @where [$($head:tt)*], [$($kind:tt)*], [$($state:tt)*], $impl:tt $ty:tt,
+        [where $($where:tt)*], $a:tt => $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            @where [$($head)*], [$($kind)*], [$($state)*], $impl $ty,
+            [where $($where)* $a], => $($rest)+
+        );
+    };
+    (
+        @where [$($head:tt)*], [$($kind:tt)*], [$($state:tt)*], $impl:tt $ty:tt,
+        [where $($where:tt)*], $a:tt $b:tt => $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            @where [$($head)*], [$($kind)*], [$($state)*], $impl $ty,
+            [where $($where)* $a $b], => $($rest)+
+        );
+    };
+    (
+        @where [$($head:tt)*], [$($kind:tt)*], [$($state:tt)*], $impl:tt $ty:tt,
+        [where $($where:tt)*], $a:tt $b:tt $c:tt => $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            @where [$($head)*], [$($kind)*], [$($state)*], $impl $ty,
+            [where $($where)* $a $b $c], => $($rest)+
+        );
+    };
+    (
+        @where [$($head:tt)*], [$($kind:tt)*], [$($state:tt)*], $impl:tt $ty:tt,
+        [where $($where:tt)*], $a:tt $b:tt $c:tt $d:tt $($rest:tt)+
+    ) => {
+        $crate::__event_generics!(
+            @where [$($head)*], [$($kind)*], [$($state)*], $impl $ty,
+            [where $($where)* $a $b $c $d], $($rest)+
+        );
+    };
+    (
+        @clauses [$($head:tt)*], [$($state:tt)*], [$($signature:tt)*],
+        [$($emit:tt)*] [$($subscriber:tt)*], ; $vis:vis emit $($rest:tt)*
+    ) => {
+        $crate::__event_generics!(
+            @clauses [$($head)*], [$($state)*], [$($signature)*],
+            [[$vis]] [$($subscriber)*], $($rest)*
+        );
+    };
+    // Subscriber without visibility has visibility of dispatcher.
+    (
+        @clauses [[$($attr:tt)*], [$($vis:tt)*], $($head:tt)*], [$($state:tt)*],
+        [$($signature:tt)*], [$($emit:tt)*] [$($subscriber:tt)*], ; subscriber $name:ident
+        $($rest:tt)*
+    ) => {
+        $crate::__event_generics!(
+            @clauses [[$($attr)*], [$($vis)*], $($head)*], [$($state)*], [$($signature)*],
+            [$($emit)*] [[$($vis)*] $name], $($rest)*
+        );
+    };
+    (
+        @clauses [$($head:tt)*], [$($state:tt)*], [$($signature:tt)*],
+        [$($emit:tt)*] [$($subscriber:tt)*], ; $vis:vis subscriber $name:ident $($rest:tt)*
+    ) => {
+        $crate::__event_generics!(
+            @clauses [$($head)*], [$($state)*], [$($signature)*],
+            [$($emit)*] [[$vis] $name], $($rest)*
+        );
+    };
+    // Emitting methods and subscriber have visibility of dispatcher by default.
+    (
+        @clauses [[$($attr:tt)*], [$($vis:tt)*], $($head:tt)*], [$($state:tt)*],
+        [$($signature:tt)*], [] [$($subscriber:tt)*],
+    ) => {
+        $crate::__event_generics!(
+            @clauses [[$($attr)*], [$($vis)*], $($head)*], [$($state)*], [$($signature)*],
+            [[$($vis)*]] [$($subscriber)*],
+        );
+    };
+    (
+        @clauses [[$($attr:tt)*], [$($vis:tt)*], $($head:tt)*], [$($state:tt)*],
+        [$($signature:tt)*], [[$($emit:tt)*]] [],
+    ) => {
+        $crate::__event_signature!(
+            [[$($attr)*], [$($vis)*], $($head)* [$($vis)*] [$($emit)*] []], $($state)*,
+            $($signature)*
+        );
+    };
+    (
+        @clauses [$($head:tt)*], [$($state:tt)*], [$($signature:tt)*],
+        [[$($emit:tt)*]] [[$($vis:tt)*] $subscriber:ident],
+    ) => {
+        $crate::__event_signature!(
+            [$($head)* [$($vis)*] [$($emit)*] [$subscriber]], $($state)*, $($signature)*
+        );
+    };
+}
+
+
+/// Picks storage, `emit` receiver and handler iteration for `Fn` and `FnMut` handlers, rejecting
+/// `AsyncFn` handlers of `Queued` dispatchers.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_signature {
+    (
+        [$($head:tt)*], [$($mut:tt)?],
+        [$fn_storage:ident, $fn_self:ty], [$fn_mut_storage:ident, $fn_mut_self:ty],
+        Fn($($args:tt)*) $emit:ident [$ret:ty] [$($bound:tt)*]
+    ) => {
+        $crate::__event_impl!(
+            [$($head)*, $fn_storage, [$($mut)?], Fn, [$($args)*], $fn_self, for_each],
+            $ret, [$($bound),*], $emit
+        );
+    };
+    (
+        [$($head:tt)*], [$($mut:tt)?],
+        [$fn_storage:ident, $fn_self:ty], [$fn_mut_storage:ident, $fn_mut_self:ty],
+        FnMut($($args:tt)*) $emit:ident [$ret:ty] [$($bound:tt)*]
+    ) => {
+        $crate::__event_impl!(
+            [
+                $($head)*, $fn_mut_storage, [$($mut)?], FnMut, [$($args)*], $fn_mut_self,
+                for_each_mut
+            ],
+            $ret, [$($bound),*], $emit
+        );
+    };
+    (
+        [$attr:tt, $vis:tt, $name:ident $impl:tt $ty:tt $where:tt, queued $($kind:tt)*],
+        [$($mut:tt)?], [$($fn:tt)*], [$($fn_mut:tt)*], AsyncFn $($rest:tt)*
+    ) => {
+        compile_error!("`Queued` dispatchers don't support `AsyncFn` handlers");
+    };
+    (
+        [$($head:tt)*], [$($mut:tt)?],
+        [$fn_storage:ident, $fn_self:ty], [$fn_mut_storage:ident, $fn_mut_self:ty],
+        AsyncFn($($args:tt)*) unit [$ret:ty] [$($bound:tt)*]
+    ) => {
+        $crate::__event_async!(
+            [$($head)*, $fn_storage, [$($mut)?], Fn, [$($args)*], $fn_self, for_each],
+            [$($bound),*], [] $(+ $bound)*
+        );
+    };
+}
+
+
+/// Derives bounds of futures returned by async handlers from handler bounds.
+///
+/// Futures are not required to be `Sync`, as they are only polled through exclusive reference.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_async {
+    ([$($head:tt)*], [$($bound:tt),*], [$($kept:tt)*] + Sync $($rest:tt)*) => {
+        $crate::__event_async!([$($head)*], [$($bound),*], [$($kept)*] $($rest)*);
+    };
+    ([$($head:tt)*], [$($bound:tt),*], [$($kept:tt)*] + $next:tt $($rest:tt)*) => {
+        $crate::__event_async!([$($head)*], [$($bound),*], [$($kept)* + $next] $($rest)*);
+    };
+    ([$($head:tt)*], [$($bound:tt),*], [$($kept:tt)*]) => {
+        $crate::__event_impl!(
+            [$($head)*],
+            ::std::pin::Pin<Box<dyn ::std::future::Future<Output = ()> $($kept)*>>,
+            [$($bound),*],
+            future
+        );
+    };
+}
+
+
+/// Splits tokens after handler arguments into return type, handler bounds and clauses following
+/// them.
+///
+/// Return type ends where the rest of tokens are bounds, each of them `+` with single token,
+/// optionally followed by clauses starting with `;`. Return type is taken by several tokens at
+/// once to keep recursion shallow.
+///
+/// `Propagation` return type is recognized as is or by path through `eventd` crate, other paths
+/// ending with `Propagation` name types of their own.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_returning {
+    (
+        [$($head:tt)*], [$($state:tt)*], [$($signature:tt)*],
+        -> $first:tt $($rest:tt)*
+    ) => {
+        $crate::__event_returning!(
+            @ret [$($head)*], [$($state)*], [$($signature)*], [$first] $($rest)*
+        );
+    };
+    (
+        [$($head:tt)*], [$($state:tt)*], [$($signature:tt)*],
+        $(+ $bound:tt)* $(; $($clauses:tt)*)?
+    ) => {
+        $crate::__event_generics!(
+            @clauses [$($head)*], [$($state)*], [$($signature)* unit [()] [$($bound)*]], [] [],
+            $(; $($clauses)*)?
+        );
+    };
+    (
+        @ret [$($head:tt)*], [$($state:tt)*], [$($signature:tt)*], [$($ret:tt)+]
+        $(+ $bound:tt)* $(; $($clauses:tt)*)?
+    ) => {
+        $crate::__event_returning!(
+            @kind [$($head)*], [$($state)*], [$($signature)*], [$($ret)+] [$($bound)*],
+            $(; $($clauses)*)?
+        );
+    };
+    (
+        @ret [$($head:tt)*], [$($state:tt)*], [$($signature:tt)*], [$($ret:tt)+]
+        $a:tt $(+ $bound:tt)* $(; $($clauses:tt)*)?
+    ) => {
+        $crate::__event_returning!(
+            @kind [$($head)*], [$($state)*], [$($signature)*], [$($ret)+ $a] [$($bound)*],
+            $(; $($clauses)*)?
+        );
+    };
+    (
+        @ret [$($head:tt)*], [$($state:tt)*], [$($signature:tt)*], [$($ret:tt)+]
+        $a:tt $b:tt $(+ $bound:tt)* $(; $($clauses:tt)*)?
+    ) => {
+        $crate::__event_returning!(
+            @kind [$($head)*], [$($state)*], [$($signature)*], [$($ret)+ $a $b] [$($bound)*],
+            $(; $($clauses)*)?
+        );
+    };
+    (
+        @ret [$($head:tt)*], [$($state:tt)*], [$($signature:tt)*], [$($ret:tt)+]
+        $a:tt $b:tt $c:tt $(+ $bound:tt)* $(; $($clauses:tt)*)?
+    ) => {
+        $crate::__event_returning!(
+            @kind [$($head)*], [$($state)*], [$($signature)*], [$($ret)+ $a $b $c] [$($bound)*],
+            $(; $($clauses)*)?
+        );
+    };
+    (
+        @ret [$($head:tt)*], [$($state:tt)*], [$($signature:tt)*], [$($ret:tt)+]
+        $a:tt $b:tt $c:tt $d:tt $($rest:tt)*
+    ) => {
+        $crate::__event_returning!(
+            @ret [$($head)*], [$($state)*], [$($signature)*], [$($ret)+ $a $b $c $d] $($rest)*
+        );
+    };
+    (
+        @kind [$($head:tt)*], [$($state:tt)*], [$($signature:tt)*], [Propagation]
+        [$($bound:tt)*], $($clauses:tt)*
+    ) => {
+        $crate::__event_generics!(
+            @clauses [$($head)*], [$($state)*],
+            [$($signature)* propagation [$crate::Propagation] [$($bound)*]], [] [], $($clauses)*
+        );
+    };
+    (
+        @kind [$($head:tt)*], [$($state:tt)*], [$($signature:tt)*], [$(::)? eventd :: Propagation]
+        [$($bound:tt)*], $($clauses:tt)*
+    ) => {
+        $crate::__event_generics!(
+            @clauses [$($head)*], [$($state)*],
+            [$($signature)* propagation [$crate::Propagation] [$($bound)*]], [] [], $($clauses)*
+        );
+    };
+    (
+        @kind [$($head:tt)*], [$($state:tt)*], [$($signature:tt)*], [$($ret:tt)+]
+        [$($bound:tt)*], $($clauses:tt)*
+    ) => {
+        $crate::__event_generics!(
+            @clauses [$($head)*], [$($state)*],
+            [$($signature)* returning [$($ret)+] [$($bound)*]], [] [], $($clauses)*
+        );
+    };
+}
+
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_impl {
+    (
+        [
+            [$(#[$attr:meta])*], [$($vis:tt)*],
+            $name:ident [$($gen:tt)*] [$($ty_gen:tt)*] [$($where:tt)*],
+            $kind:ident [$($key:ty)?] [$($param:ty)?]
+            [$($sub_vis:tt)*] [$($emit_vis:tt)*] [$($subscriber:ident)?],
+            $storage:ident, [$($mut:tt)?],
+            $fn:tt, [$($arg_name:ident : $arg_ty:ty),*], $self_ty:ty, $for_each:ident
+        ],
+        $ret:ty, [$($bound:tt),*], $emit:ident
+    ) => {
+        $crate::__event_struct!(
+            $kind,
+            [$(#[$attr])*], [$($vis)*] [$($sub_vis)*], $name [$($subscriber)?]
+            [$($gen)*] [$($ty_gen)*] [$($where)*],
+            $crate::$storage<$($param,)? Box<dyn $fn($($arg_ty),*) -> $ret $( + $bound)*>>,
+            [$($arg_ty),*]
+        );
+
+        $crate::__event_split!(
+            [$($subscriber)?], $name [$($gen)*] [$($ty_gen)*] [$($where)*],
+            {
+                /// Subscribes a closure to be called on event emmision.
+                ///
+                /// Return subscription token.
+                $($sub_vis)* fn subscribe<__F>(
+                    &$($mut)? self,
+                    handler: __F,
+                ) -> $crate::Subscription<Self>
+                where
+                    __F: $fn($($arg_ty),*) -> $ret $( + $bound)*,
+                {
+                    self.subscribe_with_priority(0, handler)
+                }
+
+                /// Subscribes a closure to be called on event emmision before handlers with
+                /// lower priority.
+                ///
+                /// Handlers subscribed with `subscribe` have priority `0`. Return subscription
+                /// token.
+                $($sub_vis)* fn subscribe_with_priority<__F>(
+                    &$($mut)? self,
+                    priority: i32,
+                    handler: __F,
+                ) -> $crate::Subscription<Self>
+                where
+                    __F: $fn($($arg_ty),*) -> $ret $( + $bound)*,
+                {
+                    self.handlers.insert(priority, Box::new(handler))
+                }
+
+                /// Subscribes a closure to be called on event emmision until returned guard is
+                /// dropped.
+                ///
+                /// Use `SubscriptionGuard::detach` to keep handler subscribed past the guard.
+                $($sub_vis)* fn subscribe_scoped<__F>(
+                    &$($mut)? self,
+                    handler: __F,
+                ) -> $crate::SubscriptionGuard<Self>
+                where
+                    __F: $fn($($arg_ty),*) -> $ret $( + $bound)*,
+                {
+                    let subscription = self.subscribe(handler);
+                    self.handlers.guard(subscription)
+                }
+
+                /// Unsubscribes handler for given subscription token.
+                ///
+                /// Returns error if there is no handler for given subscription, including the
+                /// case when subscription was issued by another dispatcher.
+                $($sub_vis)* fn unsubscribe(
+                    &$($mut)? self,
+                    subscription: $crate::Subscription<Self>,
+                ) -> Result<(), $crate::Error> {
+                    self.unsubscribe_any(subscription.erase())
+                }
+
+                /// Unsubscribes handler for given type-erased subscription token.
+                ///
+                /// Returns error if there is no handler for given subscription, including the
+                /// case when subscription was issued by another dispatcher.
+                $($sub_vis)* fn unsubscribe_any(
+                    &$($mut)? self,
+                    subscription: $crate::AnySubscription,
+                ) -> Result<(), $crate::Error> {
+                    match self.handlers.remove(&subscription) {
+                        Some(_) => Ok(()),
+                        None => Err($crate::Error::SubscriptionMissing),
+                    }
+                }
+
+                $crate::__event_keyed!(
+                    [$($sub_vis)*], $kind [$($key)?], $fn, [$($arg_ty),*], $ret, [$($bound),*]
+                );
+            },
+            [
+                [$($sub_vis)*] [$($emit_vis)*],
+                $emit -> $ret, [$($mut)?], $fn, $self_ty, $for_each, [$(key: &$key)?],
+                [$($arg_name: $arg_ty),*], [$($bound),*]
+            ],
+            [$kind, [$($vis)*] [$($emit_vis)*], [$($arg_name: $arg_ty),*]]
+        );
+    };
+}
+
+
+/// Defines methods of dispatcher, putting subscribing ones to its subscriber if it is declared.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_split {
+    (
+        [], $name:ident [$($gen:tt)*] [$($ty_gen:tt)*] [$($where:tt)*],
+        {$($subscribe:tt)*}, [$($emit:tt)*], [$($queue:tt)*]
+    ) => {
+        #[allow(dead_code)]
+        impl<$($gen)*> $name<$($ty_gen)*> $($where)* {
+            $($subscribe)*
+
+            $crate::__event_emit!(subscribe, Self, $($emit)*);
+
+            $crate::__event_emit!(emit, Self, $($emit)*);
+
+            $crate::__event_queue!($($queue)*);
+        }
+    };
+    (
+        [$subscriber:ident], $name:ident [$($gen:tt)*] [$($ty_gen:tt)*] [$($where:tt)*],
+        {$($subscribe:tt)*}, [$($emit:tt)*], [$($queue:tt)*]
+    ) => {
+        #[allow(dead_code)]
+        impl<$($gen)*> $subscriber<$($ty_gen)*> $($where)* {
+            $($subscribe)*
+
+            $crate::__event_emit!(subscribe, Self, $($emit)*);
+        }
+
+        #[allow(dead_code)]
+        impl<$($gen)*> $name<$($ty_gen)*> $($where)* {
+            $crate::__event_emit!(emit, $subscriber<$($ty_gen)*>, $($emit)*);
+
+            $crate::__event_queue!($($queue)*);
+        }
+    };
+}
+
+
+/// Defines methods of filtered subscriptions, which handlers return `$rejected` value for events
+/// rejected by predicate.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_filter {
+    (
+        [$($vis:tt)*], [$($mut:tt)?], $fn:tt -> $ret:ty, [$($arg_name:ident: $arg_ty:ty),*],
+        [$($bound:tt),*], $rejected:block
+    ) => {
+        /// Subscribes a closure to be called on event emmision if `predicate` accepts event
+        /// arguments. Return subscription token.
+        ///
+        /// Numbers of accepted and rejected events can be looked up with `filter_stats`.
+        $($vis)* fn subscribe_filtered<__P, __F>(
+            &$($mut)? self,
+            predicate: __P,
+            handler: __F,
+        ) -> $crate::Subscription<Self>
+        where
+            __P: Fn($(&$arg_ty),*) -> bool $( + $bound)*,
+            __F: $fn($($arg_ty),*) -> $ret $( + $bound)*,
+        {
+            let filter = ::std::sync::Arc::new($crate::FilterCounters::default());
+            let counters = filter.clone();
+            #[allow(unused_mut)]
+            let mut handler = handler;
+            self.handlers.insert_filtered(0, filter, Box::new(move |$($arg_name: $arg_ty),*| {
+                if counters.record(predicate($(&$arg_name),*)) {
+                    handler($($arg_name),*)
+                } else {
+                    $rejected
+                }
+            }))
+        }
+
+        /// Returns numbers of events accepted and rejected by predicate of handler subscribed
+        /// with `subscribe_filtered`.
+        ///
+        /// Returns `None` if handler is unsubscribed or was subscribed without predicate.
+        $($vis)* fn filter_stats(
+            &self,
+            subscription: &$crate::Subscription<Self>,
+        ) -> Option<$crate::FilterStats> {
+            self.handlers.filter_stats(subscription.as_any())
+        }
+    };
+}
+
+
+/// Defines methods subscribing channels, which handlers return `$continue` value.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_channel {
+    (
+        [$($vis:tt)*], [$($mut:tt)?], [$($arg_name:ident: $arg_ty:ty),*], [$($bound:tt),*],
+        $continue:block
+    ) => {
+        /// Subscribes a handler sending event arguments to returned channel.
+        ///
+        /// Handler is unsubscribed on the first event emmited after receiver is dropped. Channel
+        /// carries tuple of event arguments, so they must not borrow with elided lifetimes.
+        $($vis)* fn subscribe_channel<__M>(&$($mut)? self) -> ::std::sync::mpsc::Receiver<__M>
+        where
+            fn(__M) -> __M: Fn(($($arg_ty,)*)) -> __M,
+            fn() -> Self: $($bound +)*,
+            $crate::ChannelSender<__M>: $($bound +)*,
+        {
+            let (sender, receiver) = ::std::sync::mpsc::channel();
+            self.subscribe_sender($crate::ChannelSender::Unbounded(sender));
+            receiver
+        }
+
+        /// Subscribes a handler sending event arguments to returned channel, which holds at most
+        /// `bound` events. If channel is full, handler acts according to `backpressure`.
+        ///
+        /// Handler is unsubscribed on the first event emmited after receiver is dropped. Channel
+        /// carries tuple of event arguments, so they must not borrow with elided lifetimes.
+        $($vis)* fn subscribe_sync_channel<__M>(
+            &$($mut)? self,
+            bound: usize,
+            backpressure: $crate::Backpressure,
+        ) -> ::std::sync::mpsc::Receiver<__M>
+        where
+            fn(__M) -> __M: Fn(($($arg_ty,)*)) -> __M,
+            fn() -> Self: $($bound +)*,
+            $crate::ChannelSender<__M>: $($bound +)*,
+        {
+            let (sender, receiver) = ::std::sync::mpsc::sync_channel(bound);
+            self.subscribe_sender($crate::ChannelSender::Bounded(sender, backpressure));
+            receiver
+        }
+
+        fn subscribe_sender<__M>(&$($mut)? self, sender: $crate::ChannelSender<__M>)
+        where
+            fn(__M) -> __M: Fn(($($arg_ty,)*)) -> __M,
+            fn() -> Self: $($bound +)*,
+            $crate::ChannelSender<__M>: $($bound +)*,
+        {
+            // Identity function, which bounds above allow to call with tuple of arguments.
+            let message: fn(__M) -> __M = |args| args;
+            let guard = ::std::sync::Arc::new(::std::sync::Mutex::new(None));
+            let slot = guard.clone();
+            let handler = move |$($arg_name: $arg_ty),*| {
+                let message: &dyn Fn(($($arg_ty,)*)) -> __M = &message;
+                if !sender.send(message(($($arg_name,)*))) {
+                    drop($crate::take_once(&slot));
+                }
+                $continue
+            };
+            let subscription = self.handlers.insert::<Self>(0, Box::new(handler));
+            *guard.lock().unwrap_or_else(::std::sync::PoisonError::into_inner) =
+                Some(self.handlers.guard(subscription));
+        }
+
+        $crate::__event_stream!([$($vis)*], [$($mut)?], [$($arg_ty),*], [$($bound),*]);
+    };
+}
+
+
+/// Defines method subscribing stream, if `stream` feature is enabled.
+#[cfg(feature = "stream")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_stream {
+    ([$($vis:tt)*], [$($mut:tt)?], [$($arg_ty:ty),*], [$($bound:tt),*]) => {
+        /// Subscribes a handler passing event arguments to returned stream, which buffers at most
+        /// `capacity` events.
+        ///
+        /// If buffer is full, the oldest event is discarded and stream yields `Lagged` error.
+        /// Handler is unsubscribed on the first event emmited after stream is dropped. Stream
+        /// carries tuple of event arguments, so they must not borrow with elided lifetimes.
+        ///
+        /// Panics if `capacity` is zero.
+        $($vis)* fn subscribe_stream<__M>(
+            &$($mut)? self,
+            capacity: usize,
+        ) -> $crate::EventStream<__M>
+        where
+            fn(__M) -> __M: Fn(($($arg_ty,)*)) -> __M,
+            fn() -> Self: $($bound +)*,
+            $crate::ChannelSender<__M>: $($bound +)*,
+        {
+            let (sender, stream) = $crate::StreamSender::new(capacity);
+            self.subscribe_sender($crate::ChannelSender::Stream(sender));
+            stream
+        }
+    };
+}
+
+
+/// Defines method subscribing stream, if `stream` feature is enabled.
+#[cfg(not(feature = "stream"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_stream {
+    ([$($vis:tt)*], [$($mut:tt)?], [$($arg_ty:ty),*], [$($bound:tt),*]) => {};
+}
+
+
+/// Defines methods subscribing handlers to specific keys for `Keyed` dispatchers and to topic
+/// patterns for `Topic` dispatchers.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_keyed {
+    (
+        [$($vis:tt)*], keyed [$key:ty], $fn:tt, [$($arg_ty:ty),*], $ret:ty, [$($bound:tt),*]
+    ) => {
+        /// Subscribes a closure to be called on emmision of event with given key.
+        ///
+        /// Return subscription token.
+        $($vis)* fn subscribe_key<__F>(
+            &mut self,
+            key: $key,
+            handler: __F,
+        ) -> $crate::Subscription<Self>
+        where
+            __F: $fn($($arg_ty),*) -> $ret $( + $bound)*,
+        {
+            self.subscribe_key_with_priority(key, 0, handler)
+        }
+
+        /// Subscribes a closure to be called on emmision of event with given key before handlers
+        /// of the key with lower priority.
+        ///
+        /// Return subscription token.
+        $($vis)* fn subscribe_key_with_priority<__F>(
+            &mut self,
+            key: $key,
+            priority: i32,
+            handler: __F,
+        ) -> $crate::Subscription<Self>
+        where
+            __F: $fn($($arg_ty),*) -> $ret $( + $bound)*,
+        {
+            self.handlers.insert_key(key, priority, Box::new(handler))
+        }
+    };
+    (
+        [$($vis:tt)*], topic [$key:ty], $fn:tt, [$($arg_ty:ty),*], $ret:ty, [$($bound:tt),*]
+    ) => {
+        /// Subscribes a closure to be called on emmision of event with topic matching `pattern`.
+        ///
+        /// Return subscription token, or error if pattern is invalid.
+        $($vis)* fn subscribe_topic<__F>(
+            &mut self,
+            pattern: &str,
+            handler: __F,
+        ) -> Result<$crate::Subscription<Self>, $crate::Error>
+        where
+            __F: $fn($($arg_ty),*) -> $ret $( + $bound)*,
+        {
+            self.subscribe_topic_with_priority(pattern, 0, handler)
+        }
+
+        /// Subscribes a closure to be called on emmision of event with topic matching `pattern`
+        /// before handlers of the pattern with lower priority.
+        ///
+        /// Return subscription token, or error if pattern is invalid.
+        $($vis)* fn subscribe_topic_with_priority<__F>(
+            &mut self,
+            pattern: &str,
+            priority: i32,
+            handler: __F,
+        ) -> Result<$crate::Subscription<Self>, $crate::Error>
+        where
+            __F: $fn($($arg_ty),*) -> $ret $( + $bound)*,
+        {
+            self.handlers.insert_topic(pattern, priority, Box::new(handler))
+        }
+    };
+    (
+        [$($vis:tt)*], $kind:ident [$($key:ty)?], $fn:tt, [$($arg_ty:ty),*], $ret:ty,
+        [$($bound:tt),*]
+    ) => {};
+}
+
+
+/// Defines dispatcher struct, with queue of posted events for `Queued` dispatchers.
+///
+/// If subscriber is declared, handlers are stored by it and dispatcher holds subscriber, giving
+/// access to it through `Deref`. Subscriber is created only by dispatcher, so that holder of
+/// mutable reference to it can't replace it with an empty one. Struct storing handlers also
+/// holds marker using all generic parameters, as handlers may not use some of them.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_struct {
+    (
+        $kind:ident, [$(#[$attr:meta])*], [$($vis:tt)*] [$($sub_vis:tt)*], $name:ident
+        [$subscriber:ident] [$($gen:tt)*] [$($ty_gen:tt)*] [$($where:tt)*], $handlers:ty,
+        [$($arg_ty:ty),*]
+    ) => {
+        #[doc = concat!("Handle subscribing to [`", stringify!($name), "`] event.")]
+        $($sub_vis)* struct $subscriber<$($gen)*> $($where)* {
+            handlers: $handlers,
+            marker: $crate::__event_marker!([] $($ty_gen)*),
+        }
+
+        $crate::__event_struct!(
+            @emitter $kind, [$(#[$attr])*], [$($vis)*], $name
+            [$($gen)*] [$($ty_gen)*] [$($where)*],
+            [
+                subscriber: $subscriber<$($ty_gen)*> = $subscriber {
+                    handlers: Default::default(),
+                    marker: ::std::marker::PhantomData,
+                }
+            ],
+            [$($arg_ty),*]
+        );
+
+        impl<$($gen)*> ::std::ops::Deref for $name<$($ty_gen)*> $($where)* {
+            type Target = $subscriber<$($ty_gen)*>;
+
+            fn deref(&self) -> &Self::Target {
+                &self.subscriber
+            }
+        }
+
+        impl<$($gen)*> ::std::ops::DerefMut for $name<$($ty_gen)*> $($where)* {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.subscriber
+            }
+        }
+    };
+    (
+        $kind:ident, [$(#[$attr:meta])*], [$($vis:tt)*] [$($sub_vis:tt)*], $name:ident
+        [] [$($gen:tt)*] [$($ty_gen:tt)*] [$($where:tt)*], $handlers:ty,
+        [$($arg_ty:ty),*]
+    ) => {
+        $crate::__event_struct!(
+            @emitter $kind, [$(#[$attr])*], [$($vis)*], $name
+            [$($gen)*] [$($ty_gen)*] [$($where)*],
+            [
+                handlers: $handlers = Default::default(),
+                marker: $crate::__event_marker!([] $($ty_gen)*) = ::std::marker::PhantomData
+            ],
+            [$($arg_ty),*]
+        );
+    };
+    (
+        @emitter queued, [$(#[$attr:meta])*], [$($vis:tt)*], $name:ident
+        [$($gen:tt)*] [$($ty_gen:tt)*] [$($where:tt)*],
+        [$($field:ident: $field_ty:ty = $init:expr),+], [$($arg_ty:ty),*]
+    ) => {
+        $(#[$attr])*
+        $($vis)* struct $name<$($gen)*> $($where)* {
+            $($field: $field_ty,)+
+            queue: $crate::Queue<($($arg_ty,)*)>,
+        }
+
+        impl<$($gen)*> Default for $name<$($ty_gen)*> $($where)* {
+            fn default() -> Self {
+                $name {
+                    $($field: $init,)+
+                    queue: Default::default(),
+                }
+            }
+        }
+
+        #[allow(dead_code)]
+        impl<$($gen)*> $name<$($ty_gen)*> $($where)* {
+            /// Creates dispatcher which queues at most `capacity` posted events, handling the rest
+            /// according to `overflow`.
+            $($vis)* fn with_capacity(capacity: usize, overflow: $crate::Overflow) -> Self {
+                $name {
+                    $($field: $init,)+
+                    queue: $crate::Queue::bounded(capacity, overflow),
+                }
+            }
+        }
+    };
+    (
+        @emitter $kind:ident, [$(#[$attr:meta])*], [$($vis:tt)*], $name:ident
+        [$($gen:tt)*] [$($ty_gen:tt)*] [$($where:tt)*],
+        [$($field:ident: $field_ty:ty = $init:expr),+], [$($arg_ty:ty),*]
+    ) => {
+        $(#[$attr])*
+        $($vis)* struct $name<$($gen)*> $($where)* {
+            $($field: $field_ty,)+
+        }
+
+        impl<$($gen)*> Default for $name<$($ty_gen)*> $($where)* {
+            fn default() -> Self {
+                $name {
+                    $($field: $init,)+
+                }
+            }
+        }
+    };
+}
+
+
+/// Expands to marker type using all generic parameters of dispatcher, given by names followed by
+/// commas.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_marker {
+    ([$($marker:tt)*] $lifetime:lifetime, $($rest:tt)*) => {
+        $crate::__event_marker!(
+            [$($marker)* ::std::marker::PhantomData<&$lifetime ()>,] $($rest)*
+        )
+    };
+    ([$($marker:tt)*] $param:ident, $($rest:tt)*) => {
+        $crate::__event_marker!([$($marker)* ::std::marker::PhantomData<$param>,] $($rest)*)
+    };
+    ([$($marker:tt)*]) => {
+        ::std::marker::PhantomData<fn() -> ($($marker)*)>
+    };
+}
+
+
+/// Defines methods posting and flushing events of `Queued` dispatchers.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_queue {
+    (queued, [$($vis:tt)*] [$($emit_vis:tt)*], [$($arg_name:ident: $arg_ty:ty),*]) => {
+        /// Queues event with given arguments to be dispatched by `flush`.
+        ///
+        /// Returns error if queue is full and dispatcher was created with `Overflow::Error`.
+        $($emit_vis)* fn post(&mut self, $($arg_name: $arg_ty),*) -> Result<(), $crate::Error> {
+            self.queue.push(($($arg_name,)*))
+        }
+
+        /// Dispatches all queued events in order they were posted.
+        ///
+        /// Returns number of dispatched events.
+        $($emit_vis)* fn flush(&mut self) -> usize {
+            let mut flushed = 0;
+            while let Some(($($arg_name,)*)) = self.queue.pop() {
+                let _ = self.emit($($arg_name),*);
+                flushed += 1;
+            }
+            flushed
+        }
+
+        /// Removes all queued events without dispatching them, returning their arguments in
+        /// order they were posted.
+        $($emit_vis)* fn take_queued(&mut self) -> Vec<($($arg_ty,)*)> {
+            self.queue.take()
+        }
+
+        /// Returns number of queued events.
+        $($vis)* fn queued(&self) -> usize {
+            self.queue.len()
+        }
+    };
+    (
+        $kind:ident, [$($vis:tt)*] [$($emit_vis:tt)*], [$($arg_name:ident: $arg_ty:ty),*]
+    ) => {};
+}
+
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_emit {
+    (
+        emit, $token:ty, [$($vis:tt)*] [$($emit_vis:tt)*],
+        unit -> $ret:ty, [$($mut:tt)?], $fn:tt, $self_ty:ty, $for_each:ident,
+        [$($key:ident: $key_ty:ty)?], [$($arg_name:ident: $arg_ty:ty),*], [$($bound:tt),*]
+    ) => {
+        /// Dispatches a call with given arguments to all subscribed handlers.
+        ///
+        /// Handlers are called in order of descending priority, handlers with equal priority
+        /// are called in order of subscription.
+        ///
+        /// Arguments must be clonable. They are cloned for every handler but the last one, which
+        /// receives the original arguments.
+        $($emit_vis)* fn emit(self: $self_ty, $($key: $key_ty,)? $($arg_name: $arg_ty),*) {
+            let mut args = $crate::Args::new(($($arg_name,)*));
+            self.handlers.$for_each($($key,)? |visit, handler| {
+                let ($($arg_name,)*) = args.next(visit);
+                (*handler)($($arg_name),*);
+                $crate::Propagation::Continue
+            });
+        }
+
+        /// Dispatches a call with given arguments to all subscribed handlers, catching panics.
+        ///
+        /// Handler which panics doesn't prevent calls of other handlers. Returns caught panics
+        /// in order of calls, handlers which panicked are unsubscribed if `on_panic` says so.
+        $($emit_vis)* fn emit_catching(
+            self: $self_ty,
+            $($key: $key_ty,)?
+            $($arg_name: $arg_ty,)*
+            on_panic: $crate::OnPanic,
+        ) -> Vec<$crate::HandlerPanic<$token>> {
+            let mut panics = Vec::new();
+            let mut args = $crate::Args::new(($($arg_name,)*));
+            self.handlers.$for_each($($key,)? |visit, handler| {
+                let ($($arg_name,)*) = args.next(visit);
+                $crate::catch_panic(visit, on_panic, &mut panics, || {
+                    (*handler)($($arg_name),*);
+                    $crate::Propagation::Continue
+                })
+            });
+            panics
+        }
+    };
+    (
+        subscribe, $token:ty, [$($vis:tt)*] [$($emit_vis:tt)*],
+        unit -> $ret:ty, [$($mut:tt)?], $fn:tt, $self_ty:ty, $for_each:ident,
+        [$($key:ident: $key_ty:ty)?], [$($arg_name:ident: $arg_ty:ty),*], [$($bound:tt),*]
+    ) => {
+        /// Subscribes a closure to be called on the next event emmision only.
+        ///
+        /// Handler is unsubscribed after it is called. Return subscription token, which can be
+        /// used to unsubscribe handler before it is called.
+        $($vis)* fn subscribe_once<__F>(&$($mut)? self, handler: __F) -> $crate::Subscription<Self>
+        where
+            __F: FnOnce($($arg_ty),*) $( + $bound)*,
+            ::std::sync::Mutex<Option<__F>>: $($bound +)*,
+        {
+            let handler = ::std::sync::Mutex::new(Some(handler));
+            self.handlers.insert_once(0, Box::new(move |$($arg_name: $arg_ty),*| {
+                if let Some(handler) = $crate::take_once(&handler) {
+                    handler($($arg_name),*);
+                }
+            }))
+        }
+
+        $crate::__event_filter!(
+            [$($vis)*], [$($mut)?], $fn -> $ret, [$($arg_name: $arg_ty),*], [$($bound),*], {}
+        );
+        $crate::__event_channel!(
+            [$($vis)*], [$($mut)?], [$($arg_name: $arg_ty),*], [$($bound),*], {}
+        );
+    };
+    (
+        emit, $token:ty, [$($vis:tt)*] [$($emit_vis:tt)*],
+        propagation -> $ret:ty, [$($mut:tt)?], $fn:tt, $self_ty:ty, $for_each:ident,
+        [$($key:ident: $key_ty:ty)?], [$($arg_name:ident: $arg_ty:ty),*], [$($bound:tt),*]
+    ) => {
+        /// Dispatches a call with given arguments to subscribed handlers until one of them stops
+        /// propagation.
+        ///
+        /// Handlers are called in order of descending priority, handlers with equal priority
+        /// are called in order of subscription.
+        ///
+        /// Returns `true` if event was consumed by some handler.
+        ///
+        /// Arguments must be clonable. They are cloned for every handler but the last one, which
+        /// receives the original arguments.
+        $($emit_vis)* fn emit(self: $self_ty, $($key: $key_ty,)? $($arg_name: $arg_ty),*) -> bool {
+            let mut args = $crate::Args::new(($($arg_name,)*));
+            let propagation = self.handlers.$for_each($($key,)? |visit, handler| {
+                let ($($arg_name,)*) = args.next(visit);
+                (*handler)($($arg_name),*)
+            });
+            propagation == $crate::Propagation::Stop
+        }
+
+        /// Dispatches a call with given arguments to subscribed handlers until one of them stops
+        /// propagation, catching panics.
+        ///
+        /// Handler which panics doesn't stop propagation. Returns whether event was consumed and
+        /// caught panics in order of calls, handlers which panicked are unsubscribed if
+        /// `on_panic` says so.
+        $($emit_vis)* fn emit_catching(
+            self: $self_ty,
+            $($key: $key_ty,)?
+            $($arg_name: $arg_ty,)*
+            on_panic: $crate::OnPanic,
+        ) -> (bool, Vec<$crate::HandlerPanic<$token>>) {
+            let mut panics = Vec::new();
+            let mut args = $crate::Args::new(($($arg_name,)*));
+            let propagation = self.handlers.$for_each($($key,)? |visit, handler| {
+                let ($($arg_name,)*) = args.next(visit);
+                $crate::catch_panic(visit, on_panic, &mut panics, || {
+                    (*handler)($($arg_name),*)
+                })
+            });
+            (propagation == $crate::Propagation::Stop, panics)
+        }
+    };
+    (
+        subscribe, $token:ty, [$($vis:tt)*] [$($emit_vis:tt)*],
+        propagation -> $ret:ty, [$($mut:tt)?], $fn:tt, $self_ty:ty, $for_each:ident,
+        [$($key:ident: $key_ty:ty)?], [$($arg_name:ident: $arg_ty:ty),*], [$($bound:tt),*]
+    ) => {
+        /// Subscribes a closure to be called on the next event emmision which reaches it.
+        ///
+        /// Handler is unsubscribed after it is called. Return subscription token, which can be
+        /// used to unsubscribe handler before it is called.
+        $($vis)* fn subscribe_once<__F>(&$($mut)? self, handler: __F) -> $crate::Subscription<Self>
+        where
+            __F: FnOnce($($arg_ty),*) -> $crate::Propagation $( + $bound)*,
+            ::std::sync::Mutex<Option<__F>>: $($bound +)*,
+        {
+            let handler = ::std::sync::Mutex::new(Some(handler));
+            self.handlers.insert_once(0, Box::new(move |$($arg_name: $arg_ty),*| {
+                match $crate::take_once(&handler) {
+                    Some(handler) => handler($($arg_name),*),
+                    None => $crate::Propagation::Continue,
+                }
+            }))
+        }
+
+        $crate::__event_filter!(
+            [$($vis)*], [$($mut)?], $fn -> $ret, [$($arg_name: $arg_ty),*], [$($bound),*],
+            { $crate::Propagation::Continue }
+        );
+        $crate::__event_channel!(
+            [$($vis)*], [$($mut)?], [$($arg_name: $arg_ty),*], [$($bound),*],
+            { $crate::Propagation::Continue }
+        );
+    };
+    (
+        emit, $token:ty, [$($vis:tt)*] [$($emit_vis:tt)*],
+        returning -> $ret:ty, [$($mut:tt)?], $fn:tt, $self_ty:ty, $for_each:ident,
+        [$($key:ident: $key_ty:ty)?], [$($arg_name:ident: $arg_ty:ty),*], [$($bound:tt),*]
+    ) => {
+        /// Dispatches a call with given arguments to all subscribed handlers, discarding values
+        /// they return.
+        ///
+        /// Handlers are called in order of descending priority, handlers with equal priority
+        /// are called in order of subscription.
+        ///
+        /// Arguments must be clonable. They are cloned for every handler but the last one, which
+        /// receives the original arguments.
+        $($emit_vis)* fn emit(self: $self_ty, $($key: $key_ty,)? $($arg_name: $arg_ty),*) {
+            let mut args = $crate::Args::new(($($arg_name,)*));
+            self.handlers.$for_each($($key,)? |visit, handler| {
+                let ($($arg_name,)*) = args.next(visit);
+                let _ = (*handler)($($arg_name),*);
+                $crate::Propagation::Continue
+            });
+        }
+
+        /// Dispatches a call with given arguments to all subscribed handlers, discarding values
+        /// they return and catching panics.
+        ///
+        /// Handler which panics doesn't prevent calls of other handlers. Returns caught panics
+        /// in order of calls, handlers which panicked are unsubscribed if `on_panic` says so.
+        $($emit_vis)* fn emit_catching(
+            self: $self_ty,
+            $($key: $key_ty,)?
+            $($arg_name: $arg_ty,)*
+            on_panic: $crate::OnPanic,
+        ) -> Vec<$crate::HandlerPanic<$token>> {
+            let mut panics = Vec::new();
+            let mut args = $crate::Args::new(($($arg_name,)*));
+            self.handlers.$for_each($($key,)? |visit, handler| {
+                let ($($arg_name,)*) = args.next(visit);
+                $crate::catch_panic(visit, on_panic, &mut panics, || {
+                    let _ = (*handler)($($arg_name),*);
+                    $crate::Propagation::Continue
+                })
+            });
+            panics
+        }
+
+        /// Dispatches a call with given arguments to all subscribed handlers and returns values
+        /// they return in order of calls.
+        $($emit_vis)* fn emit_collect(
+            self: $self_ty,
+            $($key: $key_ty,)?
+            $($arg_name: $arg_ty),*
+        ) -> Vec<$ret> {
+            let mut results = Vec::new();
+            let mut args = $crate::Args::new(($($arg_name,)*));
+            self.handlers.$for_each($($key,)? |visit, handler| {
+                let ($($arg_name,)*) = args.next(visit);
+                results.push((*handler)($($arg_name),*));
+                $crate::Propagation::Continue
+            });
+            results
+        }
+
+        /// Dispatches a call with given arguments to all subscribed handlers, combining values
+        /// they return with `f` starting from `init`.
+        $($emit_vis)* fn emit_fold<__A, __G>(
+            self: $self_ty,
+            $($key: $key_ty,)?
+            $($arg_name: $arg_ty,)*
+            init: __A,
+            mut f: __G,
+        ) -> __A
+        where
+            __G: FnMut(__A, $ret) -> __A,
+        {
+            let mut accumulator = Some(init);
+            let mut args = $crate::Args::new(($($arg_name,)*));
+            self.handlers.$for_each($($key,)? |visit, handler| {
+                let ($($arg_name,)*) = args.next(visit);
+                let result = (*handler)($($arg_name),*);
+                accumulator = accumulator.take().map(|accumulator| f(accumulator, result));
+                $crate::Propagation::Continue
+            });
+            accumulator.expect("accumulator is put back after every handler")
+        }
+
+        /// Dispatches a call with given arguments to subscribed handlers until value returned by
+        /// one of them satisfies `predicate`.
+        ///
+        /// Returns `true` if such value was returned.
+        $($emit_vis)* fn emit_any<__P>(
+            self: $self_ty,
+            $($key: $key_ty,)?
+            $($arg_name: $arg_ty,)*
+            mut predicate: __P,
+        ) -> bool
+        where
+            __P: FnMut($ret) -> bool,
+        {
+            let mut args = $crate::Args::new(($($arg_name,)*));
+            self.handlers.$for_each($($key,)? |visit, handler| {
+                let ($($arg_name,)*) = args.next(visit);
+                if predicate((*handler)($($arg_name),*)) {
+                    $crate::Propagation::Stop
+                } else {
+                    $crate::Propagation::Continue
+                }
+            }) == $crate::Propagation::Stop
+        }
+
+        /// Dispatches a call with given arguments to subscribed handlers returning `Result`
+        /// until one of them fails.
+        ///
+        /// Returns first error returned by handlers.
+        $($emit_vis)* fn emit_all_ok<__V, __R>(
+            self: $self_ty,
+            $($key: $key_ty,)?
+            $($arg_name: $arg_ty),*
+        ) -> Result<(), __R>
+        where
+            $ret: Into<Result<__V, __R>>,
+        {
+            let mut error = None;
+            let mut args = $crate::Args::new(($($arg_name,)*));
+            self.handlers.$for_each($($key,)? |visit, handler| {
+                let ($($arg_name,)*) = args.next(visit);
+                match (*handler)($($arg_name),*).into() {
+                    Ok(_) => $crate::Propagation::Continue,
+                    Err(err) => {
+                        error = Some(err);
+                        $crate::Propagation::Stop
+                    }
+                }
+            });
+            match error {
+                Some(err) => Err(err),
+                None => Ok(()),
+            }
+        }
+
+        /// Dispatches a call with given arguments to all subscribed handlers returning `Result`.
+        ///
+        /// Returns errors returned by handlers along with their subscriptions, if any handler
+        /// failed.
+        $($emit_vis)* fn emit_all<__V, __R>(
+            self: $self_ty,
+            $($key: $key_ty,)?
+            $($arg_name: $arg_ty),*
+        ) -> Result<(), $crate::HandlerErrors<$token, __R>>
+        where
+            $ret: Into<Result<__V, __R>>,
+        {
+            let mut errors = Vec::new();
+            let mut args = $crate::Args::new(($($arg_name,)*));
+            self.handlers.$for_each($($key,)? |visit, handler| {
+                let ($($arg_name,)*) = args.next(visit);
+                if let Err(err) = (*handler)($($arg_name),*).into() {
+                    errors.push((visit.subscription(), err));
+                }
+                $crate::Propagation::Continue
+            });
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err($crate::HandlerErrors { errors })
+            }
+        }
+    };
+    (
+        emit, $token:ty, [$($vis:tt)*] [$($emit_vis:tt)*],
+        future -> $ret:ty, [$($mut:tt)?], $fn:tt, $self_ty:ty, $for_each:ident,
+        [$($key:ident: $key_ty:ty)?], [$($arg_name:ident: $arg_ty:ty),*], [$($bound:tt),*]
+    ) => {
+        /// Calls all subscribed handlers with given arguments and awaits returned futures one
+        /// after another.
+        ///
+        /// Handlers are called in order of descending priority, handlers with equal priority
+        /// are called in order of subscription. All of them are called when emission starts.
+        ///
+        /// Arguments must be clonable. They are cloned for every handler but the last one, which
+        /// receives the original arguments.
+        $($emit_vis)* async fn emit_async(
+            self: $self_ty,
+            $($key: $key_ty,)?
+            $($arg_name: $arg_ty),*
+        ) {
+            for future in self.emit_futures($($key,)? $($arg_name),*) {
+                future.await;
+            }
+        }
+
+        /// Calls all subscribed handlers with given arguments and awaits returned futures
+        /// concurrently.
+        ///
+        /// Handlers are called in order of descending priority, handlers with equal priority
+        /// are called in order of subscription. All of them are called when emission starts.
+        ///
+        /// Arguments must be clonable. They are cloned for every handler but the last one, which
+        /// receives the original arguments.
+        $($emit_vis)* async fn emit_async_concurrent(
+            self: $self_ty,
+            $($key: $key_ty,)?
+            $($arg_name: $arg_ty),*
+        ) {
+            $crate::JoinAll::new(self.emit_futures($($key,)? $($arg_name),*)).await;
+        }
+
+        fn emit_futures(self: $self_ty, $($key: $key_ty,)? $($arg_name: $arg_ty),*) -> Vec<$ret> {
+            let mut futures = Vec::new();
+            let mut args = $crate::Args::new(($($arg_name,)*));
+            self.handlers.$for_each($($key,)? |visit, handler| {
+                let ($($arg_name,)*) = args.next(visit);
+                futures.push((*handler)($($arg_name),*));
+                $crate::Propagation::Continue
+            });
+            futures
+        }
+    };
+    (subscribe, $($rest:tt)*) => {};
+}
+
+pub mod example {
+    //! Module with sample event.
+    //!
+    //! Generated using:
+    //!
+    //! ```
+    //! use eventd::event;
+    //!
+    //! event!(
+    //!     /// Sample event generated using `event!` macro.
+    //!     ExampleEvent<'a> => Fn(x: u32, y: &str) + Sync + 'a
+    //! );
+    //! ```
+    event!(
+        /// Sample event generated using `event!` macro.
+        ExampleEvent<'a> => Fn(x: u32, y: &str) + Sync + 'a
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_event() {
+        event!(MyEvent<'a> => FnMut(x: u8, borrowed: &str) + 'a);
+
+        let mut called1 = false;
+        let mut called2 = false;
+        let mut some_buffer = Vec::new();
+        {
+            let mut my_event = MyEvent::default();
+            my_event.subscribe(|x, y| {
+                called1 = true;
+                assert_eq!(x, 42);
+                assert_eq!(y, "foo");
+            });
+            my_event.subscribe(|x, _| {
+                called2 = true;
+                some_buffer.push(x);
+            });
+
+            my_event.emit(42, "foo");
+        }
+
+        assert!(called1);
+        assert!(called2);
+        assert_eq!(some_buffer, vec![42]);
+    }
+
+    #[test]
+    fn test_emit_clones() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        struct Payload(u32);
+
+        struct Counted(Rc<Cell<u32>>);
+
+        impl Clone for Counted {
+            fn clone(&self) -> Self {
+                self.0.set(self.0.get() + 1);
+                Counted(self.0.clone())
+            }
+        }
+
+        event!(Borrowed<'a> => FnMut(payload: &Payload, label: &String) + 'a);
+        event!(Owned => Fn(counted: Counted) + 'static);
+
+        let mut sum = 0;
+        {
+            let mut borrowed = Borrowed::default();
+            borrowed.subscribe(|payload, _| sum += payload.0);
+            borrowed.subscribe(|_, label| assert_eq!(label, "label"));
+            borrowed.emit(&Payload(2), &"label".to_string());
+        }
+        assert_eq!(sum, 2);
+
+        let clones = Rc::new(Cell::new(0));
+        let mut owned = Owned::default();
+        owned.emit(Counted(clones.clone()));
+        let _ = owned.subscribe(|_| {});
+        owned.emit(Counted(clones.clone()));
+        assert_eq!(clones.get(), 0);
+        let _ = owned.subscribe(|_| {});
+        let _ = owned.subscribe(|_| {});
+        owned.emit(Counted(clones.clone()));
+        assert_eq!(clones.get(), 2);
+    }
+
+    #[test]
+    fn test_unsubscribe() {
+        event!(MyEvent<'a> => FnMut() + 'a);
+
+        let mut called = 0u8;
+        {
+            let mut my_event = MyEvent::default();
+            let subscription = my_event.subscribe(|| {
+                called += 1;
+            });
             my_event.emit();
             my_event.emit();
             my_event.unsubscribe(subscription).unwrap();
             my_event.emit();
             my_event.emit();
         }
-        assert_eq!(called, 2);
+        assert_eq!(called, 2);
+    }
+
+    #[test]
+    fn test_unsubscribe_stale() {
+        event!(MyEvent<'a> => FnMut() + 'a);
+
+        let mut called = 0u8;
+        {
+            let mut my_event = MyEvent::default();
+            let stale = my_event.subscribe(|| {});
+            let stale = stale.erase();
+            let stale_copy = crate::AnySubscription { ..stale };
+            my_event.unsubscribe_any(stale).unwrap();
+            // Slot of removed handler is reused by the next one.
+            let _ = my_event.subscribe(|| called += 1);
+            assert!(my_event.unsubscribe_any(stale_copy).is_err());
+            my_event.emit();
+        }
+        assert_eq!(called, 1);
+    }
+
+    #[test]
+    fn test_unsubscribe_foreign() {
+        event!(MyEvent => Fn() + 'static);
+
+        event!(OtherEvent => Fn() + 'static);
+
+        let mut event1 = MyEvent::default();
+        let mut event2 = MyEvent::default();
+        let mut other = OtherEvent::default();
+        let subscription = event1.subscribe(|| {});
+        let _ = event2.subscribe(|| {});
+        let _ = other.subscribe(|| {});
+        assert!(event2.unsubscribe(subscription).is_err());
+
+        // Tokens of different events can be kept together once their type is erased.
+        let tokens = vec![event1.subscribe(|| {}).erase(), other.subscribe(|| {}).erase()];
+        let mut tokens = tokens.into_iter();
+        assert!(event1.unsubscribe_any(tokens.next().unwrap()).is_ok());
+        assert!(event1.unsubscribe_any(tokens.next().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_subscribe_scoped() {
+        use std::cell::{Cell, RefCell};
+        use std::rc::Rc;
+
+        event!(MyEvent<'a> => Fn() + 'a);
+
+        let called = Cell::new(0u8);
+        let mut my_event = MyEvent::default();
+        let guard = my_event.subscribe_scoped(|| called.set(called.get() + 1));
+        let detached = my_event.subscribe_scoped(|| called.set(called.get() + 10)).detach();
+        my_event.emit();
+        drop(guard);
+        my_event.emit();
+        assert_eq!(called.get(), 21);
+        my_event.unsubscribe(detached).unwrap();
+        my_event.emit();
+        assert_eq!(called.get(), 21);
+
+        // Handler dropping guard of another one prevents its call in the same emission, state
+        // captured by it is released on the next subscription change.
+        let later = Rc::new(RefCell::new(None));
+        let state = Rc::new(Cell::new(0u8));
+        let mut my_event = MyEvent::default();
+        let guard = later.clone();
+        let _ = my_event.subscribe_with_priority(1, move || drop(guard.borrow_mut().take()));
+        let captured = state.clone();
+        *later.borrow_mut() = Some(my_event.subscribe_scoped(move || captured.set(1)));
+        my_event.emit();
+        assert_eq!(state.get(), 0);
+        assert_eq!(Rc::strong_count(&state), 2);
+        let _ = my_event.subscribe(|| {});
+        assert_eq!(Rc::strong_count(&state), 1);
+
+        // The same holds for mutable handlers and for dispatchers calling copies of handlers.
+        event!(MyMutEvent<'a> => FnMut() + 'a);
+        event!(Shared MyShared => Fn() + 'static);
+
+        let later = Rc::new(RefCell::new(None));
+        let calls = Rc::new(Cell::new(0u8));
+        let mut my_event = MyMutEvent::default();
+        let guard = later.clone();
+        let _ = my_event.subscribe_with_priority(1, move || drop(guard.borrow_mut().take()));
+        let counter = calls.clone();
+        *later.borrow_mut() = Some(my_event.subscribe_scoped(move || counter.set(1)));
+        my_event.emit();
+        my_event.emit();
+        assert_eq!(calls.get(), 0);
+
+        let later = std::sync::Arc::new(std::sync::Mutex::new(None));
+        let state = std::sync::Arc::new(());
+        let my_shared = MyShared::default();
+        let guard = later.clone();
+        let _ = my_shared.subscribe_with_priority(1, move || drop(guard.lock().unwrap().take()));
+        let captured = state.clone();
+        *later.lock().unwrap() = Some(my_shared.subscribe_scoped(move || {
+            let _ = &captured;
+            std::unreachable!();
+        }));
+        my_shared.emit();
+        my_shared.emit();
+        let _ = my_shared.subscribe(|| {});
+        assert_eq!(std::sync::Arc::strong_count(&state), 1);
+
+        // Handler unsubscribed during emission is skipped by it, like one whose guard is dropped.
+        event!(Reentrant MyReentrant => Fn() + 'static);
+
+        let my_event = Rc::new(MyReentrant::default());
+        let later = Rc::new(RefCell::new(None));
+        let removed = Rc::new(Cell::new(None));
+        let calls = Rc::new(Cell::new(0u8));
+        let (weak, guard) = (Rc::downgrade(&my_event), later.clone());
+        let subscription = removed.clone();
+        let _ = my_event.subscribe_with_priority(1, move || {
+            if let (Some(my_event), Some(subscription)) = (weak.upgrade(), subscription.take()) {
+                my_event.unsubscribe(subscription).unwrap();
+            }
+            drop(guard.borrow_mut().take());
+        });
+        let counter = calls.clone();
+        removed.set(Some(my_event.subscribe(move || counter.set(counter.get() + 1))));
+        let counter = calls.clone();
+        let scoped = my_event.subscribe_scoped(move || counter.set(counter.get() + 10));
+        *later.borrow_mut() = Some(scoped);
+        my_event.emit();
+        assert_eq!(calls.get(), 0);
+    }
+
+    #[test]
+    fn test_priority_order() {
+        event!(MyEvent<'a> => FnMut() + 'a);
+
+        let mut calls = Vec::new();
+        {
+            let calls = std::cell::RefCell::new(&mut calls);
+            let mut my_event = MyEvent::default();
+            let removed = my_event.subscribe(|| calls.borrow_mut().push("removed"));
+            my_event.subscribe(|| calls.borrow_mut().push("first"));
+            my_event.subscribe_with_priority(-1, || calls.borrow_mut().push("last"));
+            my_event.unsubscribe(removed).unwrap();
+            // Reuses slot of removed handler, but still runs after handlers of same priority.
+            my_event.subscribe(|| calls.borrow_mut().push("second"));
+            my_event.subscribe_with_priority(10, || calls.borrow_mut().push("urgent"));
+            my_event.emit();
+        }
+        assert_eq!(calls, vec!["urgent", "first", "second", "last"]);
+    }
+
+    #[test]
+    fn test_stop_propagation() {
+        use crate::Propagation;
+
+        event!(MyEvent<'a> => FnMut(x: u8) -> Propagation + 'a);
+
+        let mut seen = Vec::new();
+        let consumed = {
+            let seen = std::cell::RefCell::new(&mut seen);
+            let mut my_event = MyEvent::default();
+            my_event.subscribe(|x| {
+                seen.borrow_mut().push(x);
+                Propagation::Continue
+            });
+            my_event.subscribe_with_priority(1, |x| {
+                if x % 2 == 0 {
+                    Propagation::Stop
+                } else {
+                    Propagation::Continue
+                }
+            });
+            [my_event.emit(1), my_event.emit(2), my_event.emit(3)]
+        };
+        assert_eq!(consumed, [false, true, false]);
+        assert_eq!(seen, vec![1, 3]);
+
+        // Other types named `Propagation` are returned as values.
+        mod ui {
+            #[derive(Debug, PartialEq)]
+            pub struct Propagation;
+        }
+
+        event!(Atomic Qualified => Fn(x: u8) -> ui::Propagation + Send + Sync + 'static);
+        event!(Absolute => Fn(x: u8) -> ::std::option::Option<Propagation> + 'static);
+
+        let qualified = Qualified::default();
+        qualified.subscribe(|_| ui::Propagation);
+        assert_eq!(qualified.emit_collect(0), vec![ui::Propagation]);
+        let mut absolute = Absolute::default();
+        absolute.subscribe(|_| None);
+        assert_eq!(absolute.emit_collect(0), vec![None]);
+    }
+
+    #[test]
+    fn test_subscribe_filtered() {
+        use crate::{FilterStats, Propagation};
+
+        event!(MyEvent<'a> => FnMut(x: u8, name: &str) -> Propagation + 'a);
+
+        let mut seen = Vec::new();
+        {
+            let mut my_event = MyEvent::default();
+            let filtered = my_event.subscribe_filtered(
+                |&x, name| x % 2 == 0 && !name.is_empty(),
+                |x, _| {
+                    seen.push(x);
+                    Propagation::Stop
+                },
+            );
+            let plain = my_event.subscribe(|_, _| Propagation::Continue);
+
+            let consumed: Vec<_> = (0..4).map(|x| my_event.emit(x, "name")).collect();
+            assert_eq!(consumed, vec![true, false, true, false]);
+            assert!(!my_event.emit(2, ""));
+            assert_eq!(
+                my_event.filter_stats(&filtered),
+                Some(FilterStats { passed: 2, rejected: 3 })
+            );
+            assert_eq!(my_event.filter_stats(&plain), None);
+        }
+        assert_eq!(seen, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_emit_catching() {
+        use std::cell::Cell;
+        use crate::OnPanic;
+
+        event!(MyEvent<'a> => FnMut(x: u8) + 'a);
+
+        let calls = Cell::new(0);
+        let mut my_event = MyEvent::default();
+        let _ = my_event.subscribe(|_| calls.set(calls.get() + 1));
+        let failing = my_event.subscribe(|x| if x > 1 { panic!("too big: {}", x) });
+        let _ = my_event.subscribe(|_| calls.set(calls.get() + 1));
+
+        assert!(my_event.emit_catching(1, OnPanic::Keep).is_empty());
+        let panics = my_event.emit_catching(2, OnPanic::Keep);
+        assert_eq!(calls.get(), 4);
+        assert_eq!(panics.len(), 1);
+        assert_eq!(panics[0].message(), Some("too big: 2"));
+
+        let panics = my_event.emit_catching(3, OnPanic::Unsubscribe);
+        assert_eq!(calls.get(), 6);
+        assert_eq!(panics.len(), 1);
+        assert!(my_event.emit_catching(4, OnPanic::Keep).is_empty());
+        assert_eq!(calls.get(), 8);
+        assert!(my_event.unsubscribe(failing).is_err());
+    }
+
+    #[test]
+    fn test_emit_returning() {
+        event!(MyEvent<'a> => FnMut(x: u32) -> Option<u32> + 'a);
+
+        let mut calls = 0;
+        let mut my_event = MyEvent::default();
+        my_event.subscribe(|x| Some(x * 2));
+        my_event.subscribe(|_| None);
+        my_event.subscribe(|x| {
+            calls += 1;
+            Some(x + 1)
+        });
+
+        assert_eq!(my_event.emit_collect(3), vec![Some(6), None, Some(4)]);
+        assert_eq!(my_event.emit_fold(3, 0, |sum, x| sum + x.unwrap_or(0)), 10);
+        assert!(my_event.emit_any(3, |x| x.is_none()));
+        assert!(!my_event.emit_any(3, |x| x == Some(0)));
+        my_event.emit(3);
+        drop(my_event);
+        // `emit_any` skipped the last handler once.
+        assert_eq!(calls, 4);
+    }
+
+    #[test]
+    fn test_emit_all() {
+        use crate::Error;
+
+        event!(MyEvent => Fn(x: u32) -> Result<(), String> + 'static);
+
+        let mut my_event = MyEvent::default();
+        let _ = my_event.subscribe(|x| if x > 1 { Err(format!("{} > 1", x)) } else { Ok(()) });
+        let failing =
+            my_event.subscribe(|x| if x > 2 { Err(format!("{} > 2", x)) } else { Ok(()) });
+
+        assert!(my_event.emit_all(1).is_ok());
+        let errors = my_event.emit_all(3).unwrap_err();
+        assert_eq!(errors.to_string(), "2 handlers failed: 3 > 1; 3 > 2");
+        assert_eq!(my_event.emit_all_ok(3), Err("3 > 1".to_string()));
+
+        let (subscription, _) = errors.errors.into_iter().nth(1).unwrap();
+        assert_eq!(my_event.unsubscribe(subscription), Ok(()));
+        assert_eq!(my_event.unsubscribe(failing), Err(Error::SubscriptionMissing));
+        assert_eq!(my_event.emit_all(3).unwrap_err().errors.len(), 1);
+    }
+
+    #[test]
+    fn test_keyed() {
+        event!(Keyed MyEvent<'a>[String] => FnMut(x: u32, name: String) + 'a);
+
+        let mut log = Vec::new();
+        {
+            let log = std::cell::RefCell::new(&mut log);
+            let mut my_event = MyEvent::default();
+            let first = my_event.subscribe_key("first".into(), |x, name| {
+                log.borrow_mut().push(format!("first {} {}", x, name));
+            });
+            my_event.subscribe_key_with_priority("first".into(), 1, |x, _| {
+                log.borrow_mut().push(format!("first priority {}", x));
+            });
+            my_event.subscribe_key("second".into(), |x, _| {
+                log.borrow_mut().push(format!("second {}", x));
+            });
+
+            my_event.emit(&"first".into(), 1, "a".into());
+            my_event.emit(&"third".into(), 2, "b".into());
+            my_event.subscribe(|x, name| log.borrow_mut().push(format!("all {} {}", x, name)));
+            my_event.emit(&"second".into(), 3, "c".into());
+            my_event.unsubscribe(first).unwrap();
+            my_event.emit(&"first".into(), 4, "d".into());
+            my_event.emit(&"third".into(), 5, "e".into());
+        }
+        assert_eq!(
+            log,
+            vec![
+                "first priority 1", "first 1 a",
+                "second 3", "all 3 c",
+                "first priority 4", "all 4 d",
+                "all 5 e",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_topic() {
+        use crate::Error;
+
+        event!(Topic MyEvent<'a> => FnMut(x: u32) + 'a);
+
+        let mut log = Vec::new();
+        {
+            let log = &std::cell::RefCell::new(&mut log);
+            let mut my_event = MyEvent::default();
+            let mut subscribe = |pattern: &'static str| {
+                my_event
+                    .subscribe_topic(pattern, move |x| log.borrow_mut().push((pattern, x)))
+                    .unwrap()
+            };
+            let _ = subscribe("a/b/c");
+            let _ = subscribe("a/+/c");
+            let _ = subscribe("a/#");
+            let _ = subscribe("+/+/+");
+            let _ = subscribe("+/b/#");
+            let any = subscribe("#");
+            let _ = subscribe("a/b");
+
+            my_event.emit("a/b/c", 1);
+            my_event.emit("a", 2);
+            my_event.emit("a/x/c/d", 3);
+            my_event.unsubscribe(any).unwrap();
+            my_event.emit("b/b", 4);
+            my_event.emit("b/c/d/e", 5);
+
+            let invalid = my_event.subscribe_topic("a/#/c", |_| {});
+            assert_eq!(invalid.unwrap_err(), Error::InvalidTopic);
+            assert!(my_event.subscribe_topic("a/b+", |_| {}).is_err());
+        }
+        assert_eq!(
+            log,
+            vec![
+                ("a/b/c", 1), ("a/+/c", 1), ("a/#", 1), ("+/b/#", 1), ("+/+/+", 1), ("#", 1),
+                ("a/#", 2), ("#", 2),
+                ("a/#", 3), ("#", 3),
+                ("+/b/#", 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generics() {
+        use std::collections::HashMap;
+        use std::error::Error;
+        use std::fmt::{Debug, Display};
+        use std::hash::Hash;
+
+        event!(Changed<T: Clone> => Fn(old: T, new: T) + 'static);
+        event!(
+            Keyed Parsed<'a, 'b, K: Eq + Hash + Clone, T: Into<Vec<u8>>>[K]
+            where
+                T: Clone + 'a,
+            => FnMut(input: &'b str, value: &'a T) -> Result<(), String> + 'a
+        );
+
+        let mut changed = Changed::default();
+        let numbers = changed.subscribe_channel();
+        changed.emit(1, 2);
+        assert_eq!(numbers.try_recv(), Ok((1, 2)));
+
+        let mut changed = Changed::<String>::default();
+        let strings = changed.subscribe_channel();
+        let _ = changed.subscribe_once(|old, _| assert_eq!(old, "a"));
+        let _ = changed.subscribe_filtered(|old, new| old != new, |_, _| {});
+        changed.emit("a".into(), "b".into());
+        assert_eq!(strings.try_recv(), Ok(("a".to_string(), "b".to_string())));
+
+        fn show<T: Display>(value: &T) -> String {
+            value.to_string()
+        }
+
+        let value = "abc".to_string();
+        let mut lengths = HashMap::new();
+        {
+            let mut parsed = Parsed::default();
+            let _ = parsed.subscribe_key('x', |input, value: &String| {
+                lengths.insert(input, value.clone().into_bytes().len());
+                Err(show(value))
+            });
+            assert_eq!(parsed.emit_all(&'x', "first", &value).unwrap_err().errors.len(), 1);
+            assert!(parsed.emit_all(&'y', "second", &value).is_ok());
+        }
+        assert_eq!(lengths, HashMap::from([("first", 3)]));
+
+        // Type parameters may have names of any single letter.
+        event!(Mapped<A: Clone, F: Clone, M: Clone, R> => Fn(a: A, f: F, m: M) -> R + 'static);
+
+        let mut mapped = Mapped::default();
+        let _ = mapped.subscribe(|a: u8, f: char, m: &str| format!("{}{}{}", a, f, m));
+        assert_eq!(mapped.emit_collect(1, 'b', "c"), vec!["1bc".to_string()]);
+
+        // Long parameter lists, where clauses and return types don't exhaust recursion limit.
+        event!(
+            Deep<A: Clone + Debug + Send, B: Clone + Hash + Eq + Send, C: Into<Vec<u8>> + Clone, D>
+            where
+                A: PartialEq + Sync + 'static,
+                B: PartialOrd + Sync + 'static,
+                C: Debug + Send + Sync + 'static,
+                D: Clone + Default + Debug + Send + Sync + 'static,
+            => Fn(a: A, b: B, c: C, d: D)
+                -> Result<Option<Vec<(A, B)>>, Box<dyn Error + Send + Sync>>
+                + Send + Sync + 'static;
+            subscriber OnDeep
+        );
+
+        let mut deep = Deep::<u8, u8, String, u8>::default();
+        let _ = deep.subscribe(|a, b, _, _| Ok(Some(vec![(a, b)])));
+        assert_eq!(deep.emit_collect(1, 2, String::new(), 0).len(), 1);
+
+        // Handlers may not use some of generic parameters.
+        event!(Tagged<'a, T> => Fn(x: u32) + 'static);
+
+        let mut tagged = Tagged::<String>::default();
+        let _ = tagged.subscribe(|x| assert_eq!(x, 1));
+        tagged.emit(1);
+
+        event!(Sent<M: Clone, P: Clone> => Fn(m: M, p: P) + 'static);
+
+        let mut sent = Sent::default();
+        let receiver = sent.subscribe_channel();
+        let _ = sent.subscribe_filtered(|&m, _| m == 1, |_, p: &str| assert_eq!(p, "a"));
+        sent.emit(1, "a");
+        assert_eq!(receiver.try_recv(), Ok((1, "a")));
+    }
+
+    #[test]
+    fn test_visibility() {
+        mod button {
+            pub(super) struct Click {
+                pub(super) x: u32,
+            }
+
+            event!(pub(super) Clicked => Fn(click: &Click) -> u32 + 'static; pub(self) emit);
+            event!(pub(crate) Queued Pressed => Fn(key: char) + 'static; emit);
+
+            #[derive(Default)]
+            pub(super) struct Button {
+                pub(super) clicked: Clicked,
+                pub(super) pressed: Pressed,
+            }
+
+            impl Button {
+                pub(super) fn click(&mut self, x: u32) -> Vec<u32> {
+                    self.pressed.post(' ').unwrap();
+                    self.pressed.flush();
+                    self.clicked.emit_collect(&Click { x })
+                }
+            }
+        }
+
+        let mut button = button::Button::default();
+        let _ = button.clicked.subscribe(|click| click.x * 2);
+        let _ = button.pressed.subscribe(|key| assert_eq!(key, ' '));
+        assert_eq!(button.click(2), vec![4]);
+        assert_eq!(button.pressed.queued(), 0);
+    }
+
+    #[test]
+    fn test_subscriber() {
+        mod button {
+            event!(
+                pub(super) Clicked<T: Copy> => Fn(x: T) -> T + 'static;
+                pub(super) subscriber OnClicked
+            );
+            event!(
+                pub(super) Queued Pressed => FnMut(key: char) + 'static;
+                pub(super) subscriber OnPressed
+            );
+            event!(
+                pub(super) Keyed Held[char] => Fn() + 'static;
+                pub(super) subscriber OnHeld
+            );
+
+            #[derive(Default)]
+            pub(super) struct Button {
+                clicked: Clicked<u32>,
+                pressed: Pressed,
+                held: Held,
+            }
+
+            impl Button {
+                pub(super) fn on_clicked(&mut self) -> &mut OnClicked<u32> {
+                    &mut self.clicked
+                }
+
+                pub(super) fn on_pressed(&mut self) -> &mut OnPressed {
+                    &mut self.pressed
+                }
+
+                pub(super) fn on_held(&mut self) -> &mut OnHeld {
+                    &mut self.held
+                }
+
+                pub(super) fn click(&mut self, x: u32) -> Vec<u32> {
+                    self.held.emit(&'x');
+                    self.pressed.post('x').unwrap();
+                    self.pressed.flush();
+                    self.clicked.emit_collect(x)
+                }
+            }
+        }
+
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let mut button = button::Button::default();
+        let subscription = button.on_clicked().subscribe(|x| x * 2);
+        let _ = button.on_clicked().subscribe(|x| x + 1);
+        let (pressed, held) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let counter = pressed.clone();
+        let _ = button.on_pressed().subscribe(move |key| {
+            assert_eq!(key, 'x');
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let counter = held.clone();
+        let _ = button.on_held().subscribe_key('x', move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let _ = button.on_held().subscribe_key('y', || panic!("handler of another key"));
+        assert_eq!(button.click(2), vec![4, 3]);
+        button.on_clicked().unsubscribe(subscription).unwrap();
+        assert_eq!(button.click(2), vec![3]);
+        assert_eq!(pressed.load(Ordering::SeqCst), 2);
+        assert_eq!(held.load(Ordering::SeqCst), 2);
+
+        // Subscriber without visibility is as visible as dispatcher.
+        event!(Moved => Fn(x: u32) + 'static; subscriber OnMoved);
+
+        let mut moved = Moved::default();
+        let on_moved: &mut OnMoved = &mut moved;
+        let _ = on_moved.subscribe(move |x| assert_eq!(x, 1));
+        moved.emit(1);
+    }
+
+    #[test]
+    fn test_reentrant() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        event!(Reentrant MyEvent => FnMut(x: u8) + 'static);
+
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let my_event = Rc::new(MyEvent::default());
+
+        let (weak, log) = (Rc::downgrade(&my_event), calls.clone());
+        my_event.subscribe(move |x| {
+            log.borrow_mut().push(("outer", x));
+            let my_event = weak.upgrade().unwrap();
+            if x == 0 {
+                // Follow-up handler doesn't see current event.
+                let log = log.clone();
+                my_event.subscribe(move |x| log.borrow_mut().push(("follow-up", x)));
+                // Nested emission skips this handler, as it is running.
+                my_event.emit(1);
+            }
+        });
+        my_event.emit(0);
+        my_event.emit(2);
+
+        assert_eq!(
+            *calls.borrow(),
+            vec![("outer", 0), ("follow-up", 1), ("outer", 2), ("follow-up", 2)]
+        );
+    }
+
+    #[test]
+    fn test_subscribe_once() {
+        use std::sync::mpsc;
+
+        event!(MyEvent => Fn(x: u8) + Send + Sync + 'static);
+        event!(MyMutEvent<'a> => FnMut(x: u8) + 'a);
+
+        let (sender, receiver) = mpsc::channel();
+        let mut my_event = MyEvent::default();
+        // Sender is moved into handler and dropped after the call.
+        let once = my_event.subscribe_once(move |x| sender.send(x).unwrap());
+        let shared = &my_event;
+        shared.emit(1);
+        shared.emit(2);
+        assert_eq!(receiver.iter().collect::<Vec<_>>(), vec![1]);
+        assert!(my_event.unsubscribe(once).is_err());
+
+        let mut calls = Vec::new();
+        {
+            let mut my_event = MyMutEvent::default();
+            let cancelled = my_event.subscribe_once(|_| std::unreachable!());
+            my_event.subscribe_once(|x| calls.push(x));
+            my_event.unsubscribe(cancelled).unwrap();
+            my_event.emit(1);
+            my_event.emit(2);
+        }
+        assert_eq!(calls, vec![1]);
+    }
+
+    #[test]
+    fn test_shared() {
+        use std::sync::{Arc, Mutex};
+        use std::thread;
+
+        event!(Shared MyEvent => FnMut(x: u32) + Send + Sync + 'static);
+
+        fn assert_sync<T: Send + Sync>(_: &T) {}
+
+        let sum = Arc::new(Mutex::new(0));
+        let my_event = Arc::new(MyEvent::default());
+        assert_sync(&*my_event);
+
+        let threads: Vec<_> = (1..=4)
+            .map(|x| {
+                let (my_event, sum) = (my_event.clone(), sum.clone());
+                thread::spawn(move || {
+                    let weak = Arc::downgrade(&my_event);
+                    // Handlers are not called under the storage lock, so they can use dispatcher.
+                    // Handler may be called by emission of another thread, but only once.
+                    my_event.subscribe_once(move |_| {
+                        weak.upgrade().unwrap().subscribe(move |y| {
+                            *sum.lock().unwrap() += x * y;
+                        });
+                    });
+                    my_event.emit(0);
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        *sum.lock().unwrap() = 0;
+        my_event.emit(1);
+        assert_eq!(*sum.lock().unwrap(), 1 + 2 + 3 + 4);
+    }
+
+    #[test]
+    fn test_shared_reentrant() {
+        use std::sync::{Arc, Mutex};
+
+        event!(Shared Nested => FnMut(depth: u32) + Send + Sync + 'static);
+        event!(Atomic AtomicNested => FnMut(depth: u32) + Send + Sync + 'static);
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let nested = Arc::new(Nested::default());
+        let atomic = Arc::new(AtomicNested::default());
+        let (weak, log) = (Arc::downgrade(&nested), calls.clone());
+        nested.subscribe(move |depth| {
+            log.lock().unwrap().push(("outer", depth));
+            if depth == 0 {
+                weak.upgrade().unwrap().emit(depth + 1);
+            }
+        });
+        let log = calls.clone();
+        nested.subscribe(move |depth| log.lock().unwrap().push(("inner", depth)));
+        let (weak, log) = (Arc::downgrade(&atomic), calls.clone());
+        atomic.subscribe(move |depth| {
+            log.lock().unwrap().push(("atomic", depth));
+            if depth == 0 {
+                weak.upgrade().unwrap().emit(depth + 1);
+            }
+        });
+
+        // Handler emitting its own dispatcher is skipped by nested emission.
+        nested.emit(0);
+        atomic.emit(0);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![("outer", 0), ("inner", 1), ("inner", 0), ("atomic", 0)],
+        );
+    }
+
+    #[test]
+    fn test_shared_nested_threads() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use std::sync::{mpsc, Arc};
+        use std::thread;
+        use std::time::Duration;
+
+        event!(Shared Nested => FnMut(depth: u32) + Send + Sync + 'static);
+        event!(Atomic AtomicNested => FnMut(depth: u32) + Send + Sync + 'static);
+
+        // Handler emitting its dispatcher from two threads skips itself without waiting.
+        fn run<E: Send + Sync + 'static>(event: Arc<E>, emit: fn(&E, u32)) -> bool {
+            let (done, finished) = mpsc::channel();
+            for _ in 0..2 {
+                let (event, done) = (event.clone(), done.clone());
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        emit(&event, 0);
+                    }
+                    done.send(()).unwrap();
+                });
+            }
+            (0..2).all(|_| finished.recv_timeout(Duration::from_secs(10)).is_ok())
+        }
+
+        let nested = Arc::new(Nested::default());
+        let atomic = Arc::new(AtomicNested::default());
+        let weak = Arc::downgrade(&nested);
+        nested.subscribe(move |depth| {
+            if depth == 0 {
+                weak.upgrade().unwrap().emit(1);
+            }
+        });
+        let weak = Arc::downgrade(&atomic);
+        atomic.subscribe(move |depth| {
+            if depth == 0 {
+                weak.upgrade().unwrap().emit(1);
+            }
+        });
+        assert!(run(nested, |event, depth| event.emit(depth)));
+        assert!(run(atomic, |event, depth| event.emit(depth)));
+
+        // Handler running on another thread is waited for by nested emission.
+        let outer = Arc::new(Nested::default());
+        let inner = Arc::new(Nested::default());
+        let seen = Arc::new(AtomicU32::new(0));
+        let (started, running) = mpsc::channel();
+        let counter = seen.clone();
+        inner.subscribe(move |depth| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            if depth == 1 {
+                started.send(()).unwrap();
+                thread::sleep(Duration::from_millis(200));
+            }
+        });
+        let nested = inner.clone();
+        outer.subscribe(move |_| nested.emit(2));
+        let (event, (done, finished)) = (inner.clone(), mpsc::channel());
+        thread::spawn(move || {
+            event.emit(1);
+            done.send(()).unwrap();
+        });
+        running.recv_timeout(Duration::from_secs(10)).unwrap();
+        outer.emit(0);
+        finished.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(seen.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_atomic() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use std::sync::Arc;
+
+        event!(Atomic MyEvent => Fn(x: u32) + Send + Sync + 'static);
+
+        let sum = Arc::new(AtomicU32::new(0));
+        let my_event = MyEvent::default();
+        let (first, second) = (sum.clone(), sum.clone());
+        let subscription = my_event.subscribe(move |x| {
+            first.fetch_add(x, Ordering::Relaxed);
+        });
+        let guard = my_event.subscribe_scoped(move |x| {
+            second.fetch_add(x * 10, Ordering::Relaxed);
+        });
+        my_event.emit(1);
+        drop(guard);
+        my_event.emit(2);
+        // Emission skips detached handler, next subscription change releases it.
+        assert_eq!(Arc::strong_count(&sum), 3);
+        my_event.unsubscribe(subscription).unwrap();
+        assert_eq!(Arc::strong_count(&sum), 1);
+        my_event.emit(3);
+        assert_eq!(sum.load(Ordering::Relaxed), 1 + 10 + 2);
+    }
+
+    #[test]
+    fn test_emit_async() {
+        use std::cell::RefCell;
+        use std::future::Future;
+        use std::pin::Pin;
+        use std::rc::Rc;
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake, Waker};
+
+        struct NoopWaker;
+
+        impl Wake for NoopWaker {
+            fn wake(self: Arc<Self>) {}
+        }
+
+        fn block_on<F: Future>(future: F) -> F::Output {
+            let waker = Waker::from(Arc::new(NoopWaker));
+            let mut cx = Context::from_waker(&waker);
+            let mut future = Box::pin(future);
+            loop {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                    return output;
+                }
+            }
+        }
+
+        /// Returns `Pending` once, letting other futures run.
+        struct YieldNow(bool);
+
+        impl Future for YieldNow {
+            type Output = ();
+
+            fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+                if self.0 {
+                    Poll::Ready(())
+                } else {
+                    self.0 = true;
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            }
+        }
+
+        event!(MyEvent => AsyncFn(name: &'static str) + 'static);
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut my_event = MyEvent::default();
+        for (start, end) in [("start 1", "end 1"), ("start 2", "end 2")] {
+            let log = log.clone();
+            my_event.subscribe(move |_| {
+                let log = log.clone();
+                Box::pin(async move {
+                    log.borrow_mut().push(start);
+                    YieldNow(false).await;
+                    log.borrow_mut().push(end);
+                })
+            });
+        }
+
+        block_on(my_event.emit_async("sequential"));
+        assert_eq!(*log.borrow(), vec!["start 1", "end 1", "start 2", "end 2"]);
+        log.borrow_mut().clear();
+
+        block_on(my_event.emit_async_concurrent("concurrent"));
+        assert_eq!(*log.borrow(), vec!["start 1", "start 2", "end 1", "end 2"]);
+    }
+
+    #[test]
+    fn test_queued() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        use crate::{Error, Overflow};
+
+        event!(Queued MyEvent => Fn(id: u32, name: String) + 'static);
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut my_event = MyEvent::default();
+        let sink = log.clone();
+        let _ = my_event.subscribe(move |id, name| sink.borrow_mut().push((id, name)));
+
+        my_event.post(1, "one".into()).unwrap();
+        my_event.post(2, "two".into()).unwrap();
+        assert!(log.borrow().is_empty());
+        assert_eq!(my_event.queued(), 2);
+        assert_eq!(my_event.flush(), 2);
+        assert_eq!(*log.borrow(), vec![(1, "one".to_string()), (2, "two".to_string())]);
+        assert_eq!(my_event.flush(), 0);
+
+        my_event.post(3, "three".into()).unwrap();
+        assert_eq!(my_event.take_queued(), vec![(3, "three".to_string())]);
+        assert_eq!(my_event.flush(), 0);
+
+        let posted = |overflow| {
+            let mut my_event = MyEvent::with_capacity(2, overflow);
+            let results: Vec<_> = (0..3).map(|id| my_event.post(id, String::new())).collect();
+            let ids: Vec<_> = my_event.take_queued().into_iter().map(|(id, _)| id).collect();
+            (results.into_iter().filter(Result::is_err).count(), ids)
+        };
+        assert_eq!(posted(Overflow::DropOldest), (0, vec![1, 2]));
+        assert_eq!(posted(Overflow::DropNewest), (0, vec![0, 1]));
+        assert_eq!(posted(Overflow::Error), (1, vec![0, 1]));
+        let mut full = MyEvent::with_capacity(0, Overflow::Error);
+        assert!(matches!(full.post(0, String::new()), Err(Error::QueueFull)));
+    }
+
+    #[test]
+    fn test_subscribe_channel() {
+        use std::thread;
+        use crate::Backpressure;
+
+        event!(MyEvent => Fn(x: u32, name: String) + 'static);
+        event!(Shared MyShared => Fn(x: u32) -> Propagation + Send + Sync + 'static);
+
+        let mut my_event = MyEvent::default();
+        let receiver = my_event.subscribe_channel();
+        let dropped = my_event.subscribe_channel::<(u32, String)>();
+        drop(dropped);
+        my_event.emit(1, "one".into());
+        my_event.emit(2, "two".into());
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![(1, "one".to_string()), (2, "two".to_string())]
+        );
+
+        let my_shared = MyShared::default();
+        let receiver = my_shared.subscribe_sync_channel(1, Backpressure::Drop);
+        assert!(!my_shared.emit(1));
+        assert!(!my_shared.emit(2));
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![(1,)]);
+
+        let receiver = my_shared.subscribe_sync_channel(0, Backpressure::Block);
+        let worker = thread::spawn(move || receiver.recv().unwrap());
+        my_shared.emit(3);
+        assert_eq!(worker.join().unwrap(), (3,));
+        // Receiver is dropped, so emission doesn't block and unsubscribes handler.
+        my_shared.emit(4);
+        my_shared.emit(5);
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn test_subscribe_stream() {
+        use std::pin::Pin;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake, Waker};
+        use futures_core::Stream;
+        use crate::Lagged;
+
+        struct CountingWaker(AtomicUsize);
+
+        impl Wake for CountingWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        event!(MyEvent => Fn(x: u32) + Send + Sync + 'static);
+
+        let wakes = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut my_event = MyEvent::default();
+        let mut stream = my_event.subscribe_stream(2);
+        let mut next = move || Pin::new(&mut stream).poll_next(&mut cx);
+
+        assert_eq!(next(), Poll::Pending);
+        my_event.emit(1);
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 1);
+        assert_eq!(next(), Poll::Ready(Some(Ok((1,)))));
+
+        for x in 2..6 {
+            my_event.emit(x);
+        }
+        assert_eq!(next(), Poll::Ready(Some(Err(Lagged(2)))));
+        assert_eq!(next(), Poll::Ready(Some(Ok((4,)))));
+        assert_eq!(next(), Poll::Ready(Some(Ok((5,)))));
+        assert_eq!(next(), Poll::Pending);
+
+        drop(my_event);
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 2);
+        assert_eq!(next(), Poll::Ready(None));
     }
 }
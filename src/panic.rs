@@ -0,0 +1,73 @@
+//! Isolation of panicking handlers.
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::handlers::Visit;
+use crate::{Propagation, Subscription};
+
+
+/// What `emit_catching` does with handlers which panicked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnPanic {
+    /// Keep handler subscribed.
+    Keep,
+    /// Unsubscribe handler, so it is not called by following emissions.
+    Unsubscribe,
+}
+
+
+/// Panic caught by `emit_catching` of dispatcher `E`.
+pub struct HandlerPanic<E> {
+    /// Subscription of handler which panicked.
+    pub subscription: Subscription<E>,
+    /// Payload the handler panicked with.
+    pub payload: Box<dyn Any + Send + 'static>,
+}
+
+impl<E> HandlerPanic<E> {
+    /// Returns panic message if handler panicked with string payload.
+    pub fn message(&self) -> Option<&str> {
+        match self.payload.downcast_ref::<&'static str>() {
+            Some(message) => Some(message),
+            None => self.payload.downcast_ref::<String>().map(String::as_str),
+        }
+    }
+}
+
+impl<E> fmt::Debug for HandlerPanic<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HandlerPanic")
+            .field("subscription", &self.subscription)
+            .field("message", &self.message())
+            .finish()
+    }
+}
+
+
+/// Calls handler through `call`, recording panic instead of unwinding.
+///
+/// Handler which panicked doesn't stop propagation.
+#[doc(hidden)]
+pub fn catch_panic<E>(
+    visit: &Visit,
+    on_panic: OnPanic,
+    panics: &mut Vec<HandlerPanic<E>>,
+    call: impl FnOnce() -> Propagation,
+) -> Propagation {
+    // Handlers are boxed closures that may be left in inconsistent state by panic; caller
+    // decides whether to keep them with `on_panic`.
+    match panic::catch_unwind(AssertUnwindSafe(call)) {
+        Ok(propagation) => propagation,
+        Err(payload) => {
+            if on_panic == OnPanic::Unsubscribe {
+                visit.detach();
+            }
+            panics.push(HandlerPanic {
+                subscription: visit.subscription(),
+                payload,
+            });
+            Propagation::Continue
+        }
+    }
+}
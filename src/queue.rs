@@ -0,0 +1,73 @@
+//! Queue of events posted to `Queued` dispatchers.
+use std::collections::VecDeque;
+
+use crate::Error;
+
+
+/// What `Queued` dispatcher with limited capacity does with events posted to full queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Remove the oldest queued event to make room for posted one.
+    DropOldest,
+    /// Discard posted event.
+    DropNewest,
+    /// Discard posted event and return `Error::QueueFull`.
+    Error,
+}
+
+
+/// FIFO queue of event arguments with optional capacity.
+#[doc(hidden)]
+pub struct Queue<A> {
+    items: VecDeque<A>,
+    capacity: Option<usize>,
+    overflow: Overflow,
+}
+
+impl<A> Default for Queue<A> {
+    fn default() -> Self {
+        Queue {
+            items: VecDeque::new(),
+            capacity: None,
+            overflow: Overflow::Error,
+        }
+    }
+}
+
+impl<A> Queue<A> {
+    pub fn bounded(capacity: usize, overflow: Overflow) -> Self {
+        Queue {
+            items: VecDeque::with_capacity(capacity),
+            capacity: Some(capacity),
+            overflow,
+        }
+    }
+
+    pub fn push(&mut self, item: A) -> Result<(), Error> {
+        if self.capacity.is_some_and(|capacity| self.items.len() >= capacity) {
+            match self.overflow {
+                Overflow::DropOldest if self.items.pop_front().is_some() => {}
+                Overflow::DropOldest | Overflow::DropNewest => return Ok(()),
+                Overflow::Error => return Err(Error::QueueFull),
+            }
+        }
+        self.items.push_back(item);
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<A> {
+        self.items.pop_front()
+    }
+
+    pub fn take(&mut self) -> Vec<A> {
+        self.items.drain(..).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
@@ -0,0 +1,95 @@
+//! Handler storage of dispatchers which can be used from their own handlers.
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::filter::FilterCounters;
+use crate::handlers::{Handlers, Visit};
+use crate::{AnySubscription, FilterStats, Propagation, Subscription, SubscriptionGuard};
+
+
+/// Handlers accessible through shared reference.
+///
+/// Storage is borrowed only while handlers are added, removed or copied, never while they are
+/// called. Emission calls copy of handlers taken when it started, so handlers subscribed by
+/// handlers are called by the next emission, while unsubscribed ones are skipped right away.
+#[doc(hidden)]
+pub struct Reentrant<T> {
+    handlers: RefCell<Handlers<Rc<RefCell<T>>>>,
+}
+
+impl<T> Default for Reentrant<T> {
+    fn default() -> Self {
+        Reentrant {
+            handlers: RefCell::default(),
+        }
+    }
+}
+
+impl<T> Reentrant<T> {
+    pub fn insert<E>(&self, priority: i32, handler: T) -> Subscription<E> {
+        self.handlers.borrow_mut().insert(priority, Rc::new(RefCell::new(handler)))
+    }
+
+    pub fn insert_once<E>(&self, priority: i32, handler: T) -> Subscription<E> {
+        self.handlers.borrow_mut().insert_once(priority, Rc::new(RefCell::new(handler)))
+    }
+
+    pub fn insert_filtered<E>(
+        &self,
+        priority: i32,
+        filter: Arc<FilterCounters>,
+        handler: T,
+    ) -> Subscription<E> {
+        let handler = Rc::new(RefCell::new(handler));
+        self.handlers.borrow_mut().insert_filtered(priority, filter, handler)
+    }
+
+    pub fn guard<E>(&self, subscription: Subscription<E>) -> SubscriptionGuard<E> {
+        self.handlers.borrow().guard(subscription)
+    }
+
+    pub fn remove(&self, subscription: &AnySubscription) -> Option<Rc<RefCell<T>>> {
+        self.handlers.borrow_mut().remove(subscription)
+    }
+
+    pub fn filter_stats(&self, subscription: &AnySubscription) -> Option<FilterStats> {
+        self.handlers.borrow().filter_stats(subscription)
+    }
+
+    pub fn for_each(&self, mut f: impl FnMut(&Visit, &T) -> Propagation) -> Propagation {
+        let snapshot = self.handlers.borrow_mut().snapshot();
+        let count = snapshot.len();
+        for (position, entry) in snapshot.into_iter().enumerate() {
+            if entry.is_detached() {
+                continue;
+            }
+            let propagation = f(&entry.visit(position + 1 == count), &entry.handler.borrow());
+            entry.called();
+            if propagation == Propagation::Stop {
+                return Propagation::Stop;
+            }
+        }
+        Propagation::Continue
+    }
+
+    /// Like `for_each`, but skips handlers which are already running in outer emission.
+    pub fn for_each_mut(&self, mut f: impl FnMut(&Visit, &mut T) -> Propagation) -> Propagation {
+        let snapshot = self.handlers.borrow_mut().snapshot();
+        let count = snapshot.len();
+        for (position, entry) in snapshot.into_iter().enumerate() {
+            if entry.is_detached() {
+                continue;
+            }
+            let propagation = match entry.handler.try_borrow_mut() {
+                Ok(mut handler) => f(&entry.visit(position + 1 == count), &mut handler),
+                Err(_) => continue,
+            };
+            entry.called();
+            if propagation == Propagation::Stop {
+                return Propagation::Stop;
+            }
+        }
+        Propagation::Continue
+    }
+}
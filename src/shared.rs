@@ -0,0 +1,196 @@
+//! Handler storage of dispatchers which can be shared between threads.
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::handlers::{Handlers, Visit};
+use crate::filter::FilterCounters;
+use crate::{AnySubscription, FilterStats, Propagation, Subscription, SubscriptionGuard};
+
+
+/// Immutable handlers accessible through shared reference from multiple threads.
+///
+/// Storage is locked only while handlers are added, removed or copied, never while they are
+/// called. Emission calls copy of handlers taken when it started, so handlers subscribed
+/// concurrently with it are called by the next emission, while unsubscribed ones are skipped by
+/// it unless their turn has already come.
+#[doc(hidden)]
+pub struct Shared<T> {
+    handlers: Mutex<Handlers<Arc<T>>>,
+}
+
+impl<T> Default for Shared<T> {
+    fn default() -> Self {
+        Shared {
+            handlers: Mutex::default(),
+        }
+    }
+}
+
+impl<T> Shared<T> {
+    pub fn insert<E>(&self, priority: i32, handler: T) -> Subscription<E> {
+        self.lock().insert(priority, Arc::new(handler))
+    }
+
+    pub fn insert_once<E>(&self, priority: i32, handler: T) -> Subscription<E> {
+        self.lock().insert_once(priority, Arc::new(handler))
+    }
+
+    pub fn insert_filtered<E>(
+        &self,
+        priority: i32,
+        filter: Arc<FilterCounters>,
+        handler: T,
+    ) -> Subscription<E> {
+        self.lock().insert_filtered(priority, filter, Arc::new(handler))
+    }
+
+    pub fn guard<E>(&self, subscription: Subscription<E>) -> SubscriptionGuard<E> {
+        self.lock().guard(subscription)
+    }
+
+    pub fn remove(&self, subscription: &AnySubscription) -> Option<Arc<T>> {
+        self.lock().remove(subscription)
+    }
+
+    pub fn filter_stats(&self, subscription: &AnySubscription) -> Option<FilterStats> {
+        self.lock().filter_stats(subscription)
+    }
+
+    pub fn for_each(&self, mut f: impl FnMut(&Visit, &T) -> Propagation) -> Propagation {
+        let snapshot = self.lock().snapshot();
+        let count = snapshot.len();
+        for (position, entry) in snapshot.into_iter().enumerate() {
+            if entry.is_detached() {
+                continue;
+            }
+            let propagation = f(&entry.visit(position + 1 == count), &entry.handler);
+            entry.called();
+            if propagation == Propagation::Stop {
+                return Propagation::Stop;
+            }
+        }
+        Propagation::Continue
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Handlers<Arc<T>>> {
+        // Handlers are never called under the lock, so poisoning can't leave storage inconsistent.
+        self.handlers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+
+thread_local! {
+    /// Addresses of `Exclusive` handlers held by current thread.
+    static HELD: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+
+/// Mutable handler locked while it is called.
+///
+/// Handler held by current thread is skipped by emission nested in it instead of waiting for the
+/// lock it holds. Handlers held by other threads are waited for.
+#[doc(hidden)]
+pub struct Exclusive<T> {
+    handler: Mutex<T>,
+}
+
+impl<T> Exclusive<T> {
+    pub(crate) fn new(handler: T) -> Self {
+        Exclusive {
+            handler: Mutex::new(handler),
+        }
+    }
+
+    /// Calls `f` with locked handler, waiting for other threads calling it.
+    ///
+    /// Returns `None` if handler is already running on current thread.
+    pub(crate) fn call<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let address = self as *const Self as usize;
+        if HELD.with(|held| held.borrow().contains(&address)) {
+            return None;
+        }
+        let mut handler = self.handler.lock().unwrap_or_else(PoisonError::into_inner);
+        let _held = Held::new(address);
+        Some(f(&mut handler))
+    }
+}
+
+
+/// Marks handler as held by current thread, until dropped, even by panic.
+struct Held {
+    address: usize,
+}
+
+impl Held {
+    fn new(address: usize) -> Self {
+        HELD.with(|held| held.borrow_mut().push(address));
+        Held { address }
+    }
+}
+
+impl Drop for Held {
+    fn drop(&mut self) {
+        HELD.with(|held| {
+            let mut held = held.borrow_mut();
+            if let Some(position) = held.iter().rposition(|&address| address == self.address) {
+                held.remove(position);
+            }
+        });
+    }
+}
+
+
+/// Mutable handlers accessible through shared reference from multiple threads.
+///
+/// Same as `Shared`, but every handler is locked while it is called, so concurrent emissions
+/// call it one at a time. Emission made by a handler skips handlers which are already running on
+/// its thread.
+#[doc(hidden)]
+pub struct SharedMut<T> {
+    handlers: Shared<Exclusive<T>>,
+}
+
+impl<T> Default for SharedMut<T> {
+    fn default() -> Self {
+        SharedMut {
+            handlers: Shared::default(),
+        }
+    }
+}
+
+impl<T> SharedMut<T> {
+    pub fn insert<E>(&self, priority: i32, handler: T) -> Subscription<E> {
+        self.handlers.insert(priority, Exclusive::new(handler))
+    }
+
+    pub fn insert_once<E>(&self, priority: i32, handler: T) -> Subscription<E> {
+        self.handlers.insert_once(priority, Exclusive::new(handler))
+    }
+
+    pub fn insert_filtered<E>(
+        &self,
+        priority: i32,
+        filter: Arc<FilterCounters>,
+        handler: T,
+    ) -> Subscription<E> {
+        self.handlers.insert_filtered(priority, filter, Exclusive::new(handler))
+    }
+
+    pub fn guard<E>(&self, subscription: Subscription<E>) -> SubscriptionGuard<E> {
+        self.handlers.guard(subscription)
+    }
+
+    pub fn remove(&self, subscription: &AnySubscription) -> Option<Arc<Exclusive<T>>> {
+        self.handlers.remove(subscription)
+    }
+
+    pub fn filter_stats(&self, subscription: &AnySubscription) -> Option<FilterStats> {
+        self.handlers.filter_stats(subscription)
+    }
+
+    pub fn for_each_mut(&self, mut f: impl FnMut(&Visit, &mut T) -> Propagation) -> Propagation {
+        self.handlers.for_each(|visit, handler| {
+            handler.call(|handler| f(visit, handler)).unwrap_or(Propagation::Continue)
+        })
+    }
+}
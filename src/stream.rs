@@ -0,0 +1,150 @@
+//! Stream of events for async consumers.
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+
+
+/// Error yielded by `EventStream` when it skipped events because its buffer was full.
+///
+/// Holds number of skipped events. Stream continues with the oldest event it still holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lagged(pub u64);
+
+impl fmt::Display for Lagged {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Event stream lagged behind by {} events", self.0)
+    }
+}
+
+impl std::error::Error for Lagged {}
+
+
+struct State<T> {
+    buffer: VecDeque<T>,
+    capacity: usize,
+    lagged: u64,
+    waker: Option<Waker>,
+    sender_dropped: bool,
+    stream_dropped: bool,
+}
+
+
+/// Stream of event arguments returned by `subscribe_stream`.
+///
+/// Holds at most given number of events. When handler receives event while buffer is full, the
+/// oldest buffered event is discarded, and stream yields `Lagged` error before the next event.
+/// Stream ends when its handler is unsubscribed or dispatcher is dropped.
+pub struct EventStream<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+impl<T> EventStream<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> Stream for EventStream<T> {
+    type Item = Result<T, Lagged>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.lock();
+        if state.lagged > 0 {
+            let lagged = Lagged(state.lagged);
+            state.lagged = 0;
+            return Poll::Ready(Some(Err(lagged)));
+        }
+        match state.buffer.pop_front() {
+            Some(item) => Poll::Ready(Some(Ok(item))),
+            None if state.sender_dropped => Poll::Ready(None),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Drop for EventStream<T> {
+    fn drop(&mut self) {
+        self.lock().stream_dropped = true;
+    }
+}
+
+impl<T> fmt::Debug for EventStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("EventStream")
+            .field("buffered", &state.buffer.len())
+            .field("capacity", &state.capacity)
+            .field("lagged", &state.lagged)
+            .finish()
+    }
+}
+
+
+/// Handler side of `EventStream`.
+#[doc(hidden)]
+pub struct StreamSender<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+impl<T> StreamSender<T> {
+    /// Creates stream holding at most `capacity` events and sender feeding it.
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> (Self, EventStream<T>) {
+        assert!(capacity > 0, "event stream capacity must be positive");
+        let state = Arc::new(Mutex::new(State {
+            buffer: VecDeque::with_capacity(capacity),
+            capacity,
+            lagged: 0,
+            waker: None,
+            sender_dropped: false,
+            stream_dropped: false,
+        }));
+        (StreamSender { state: state.clone() }, EventStream { state })
+    }
+
+    /// Buffers message, discarding the oldest one if buffer is full. Returns `false` if stream
+    /// was dropped.
+    pub fn send(&self, message: T) -> bool {
+        let mut state = self.lock();
+        if state.stream_dropped {
+            return false;
+        }
+        if state.buffer.len() == state.capacity {
+            state.buffer.pop_front();
+            state.lagged += 1;
+        }
+        state.buffer.push_back(message);
+        // Waker is called without lock, as it may poll the stream right away.
+        let waker = state.waker.take();
+        drop(state);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        true
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> Drop for StreamSender<T> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut state = self.lock();
+            state.sender_dropped = true;
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
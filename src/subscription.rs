@@ -0,0 +1,111 @@
+//! Subscription tokens returned by event dispatchers.
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Weak;
+
+use crate::handlers::Detached;
+
+
+/// Token used to differentiate subscribers.
+///
+/// Should be passed back to event dispatcher `E` to unsubscribe. Token remembers dispatcher which
+/// issued it and generation of its handler, so it can't remove handler of another dispatcher or
+/// handler subscribed after the original one was removed.
+///
+/// Use `erase` to store tokens of different events together.
+///
+/// Passing token to dispatcher of another event type doesn't compile:
+///
+/// ```compile_fail
+/// use eventd::event;
+///
+/// event!(Event1 => Fn() + 'static);
+/// event!(Event2 => Fn() + 'static);
+///
+/// let mut event1 = Event1::default();
+/// let mut event2 = Event2::default();
+/// let subscription = event1.subscribe(|| {});
+/// event2.unsubscribe(subscription).unwrap();
+/// ```
+pub struct Subscription<E> {
+    raw: AnySubscription,
+    event: PhantomData<fn() -> E>,
+}
+
+impl<E> Subscription<E> {
+    pub(crate) fn new(raw: AnySubscription) -> Self {
+        Subscription {
+            raw,
+            event: PhantomData,
+        }
+    }
+
+    /// Converts token into one which doesn't carry event type.
+    pub fn erase(self) -> AnySubscription {
+        self.raw
+    }
+
+    #[doc(hidden)]
+    pub fn as_any(&self) -> &AnySubscription {
+        &self.raw
+    }
+}
+
+impl<E> From<Subscription<E>> for AnySubscription {
+    fn from(subscription: Subscription<E>) -> Self {
+        subscription.erase()
+    }
+}
+
+impl<E> fmt::Debug for Subscription<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Subscription").field(&self.raw).finish()
+    }
+}
+
+
+/// Subscription token with erased event type.
+///
+/// Can be passed to `unsubscribe_any` of any dispatcher; it is rejected at runtime by dispatchers
+/// which did not issue it.
+#[derive(Debug)]
+pub struct AnySubscription {
+    pub(crate) owner: u64,
+    pub(crate) key: usize,
+    pub(crate) generation: u64,
+}
+
+
+/// Subscription which unsubscribes its handler when dropped.
+///
+/// Handler is not called by dispatcher after the guard is dropped. Dispatcher releases the
+/// handler itself on its next subscription change or mutable emission, so state captured by the
+/// handler outlives the guard until then.
+pub struct SubscriptionGuard<E> {
+    pub(crate) subscription: Option<Subscription<E>>,
+    pub(crate) detached: Weak<Detached>,
+}
+
+impl<E> SubscriptionGuard<E> {
+    /// Disarms the guard and returns plain subscription token.
+    ///
+    /// Handler will stay subscribed until the token is passed back to dispatcher.
+    pub fn detach(mut self) -> Subscription<E> {
+        self.subscription.take().expect("subscription is taken only once")
+    }
+}
+
+impl<E> Drop for SubscriptionGuard<E> {
+    fn drop(&mut self) {
+        if let (Some(_), Some(detached)) = (self.subscription.take(), self.detached.upgrade()) {
+            detached.detach();
+        }
+    }
+}
+
+impl<E> fmt::Debug for SubscriptionGuard<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("SubscriptionGuard").field(&self.subscription).finish()
+    }
+}
+
@@ -0,0 +1,307 @@
+//! Handler storage of dispatchers routing events by hierarchical topics.
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::filter::FilterCounters;
+use crate::handlers::{Handlers, Visit};
+use crate::{AnySubscription, Error, FilterStats, Propagation, Subscription, SubscriptionGuard};
+
+
+/// Level of topic pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    /// `+`, matching any single level.
+    Any,
+    /// `#`, matching any number of levels at the end of topic.
+    Rest,
+}
+
+impl Segment {
+    fn parse(pattern: &str) -> Result<Vec<Segment>, Error> {
+        let segments: Vec<_> = pattern
+            .split('/')
+            .map(|level| match level {
+                "+" => Ok(Segment::Any),
+                "#" => Ok(Segment::Rest),
+                _ if level.contains(['+', '#']) => Err(Error::InvalidTopic),
+                _ => Ok(Segment::Literal(level.to_string())),
+            })
+            .collect::<Result<_, _>>()?;
+        match segments.iter().position(|segment| *segment == Segment::Rest) {
+            Some(position) if position + 1 != segments.len() => Err(Error::InvalidTopic),
+            _ => Ok(segments),
+        }
+    }
+}
+
+
+/// Trie node holding handlers of patterns which end at it.
+struct Node<T> {
+    exact: Option<Handlers<T>>,
+    rest: Option<Handlers<T>>,
+    literal: HashMap<String, Node<T>>,
+    any: Option<Box<Node<T>>>,
+}
+
+impl<T> Default for Node<T> {
+    fn default() -> Self {
+        Node {
+            exact: None,
+            rest: None,
+            literal: HashMap::new(),
+            any: None,
+        }
+    }
+}
+
+impl<T> Node<T> {
+    fn is_empty(&self) -> bool {
+        self.exact.is_none() && self.rest.is_none() && self.literal.is_empty() && self.any.is_none()
+    }
+
+    /// Returns handlers of given pattern, creating missing nodes.
+    fn handlers_mut(&mut self, pattern: &[Segment]) -> &mut Option<Handlers<T>> {
+        match pattern.split_first() {
+            None => &mut self.exact,
+            Some((Segment::Rest, _)) => &mut self.rest,
+            Some((Segment::Any, tail)) => {
+                self.any.get_or_insert_with(Box::default).handlers_mut(tail)
+            }
+            Some((Segment::Literal(level), tail)) => {
+                self.literal.entry(level.clone()).or_default().handlers_mut(tail)
+            }
+        }
+    }
+
+    fn handlers(&self, pattern: &[Segment]) -> Option<&Handlers<T>> {
+        match pattern.split_first() {
+            None => self.exact.as_ref(),
+            Some((Segment::Rest, _)) => self.rest.as_ref(),
+            Some((Segment::Any, tail)) => self.any.as_ref()?.handlers(tail),
+            Some((Segment::Literal(level), tail)) => self.literal.get(level)?.handlers(tail),
+        }
+    }
+
+    /// Removes handler from handlers of given pattern, pruning nodes left empty.
+    fn remove(&mut self, pattern: &[Segment], subscription: &AnySubscription) -> Option<T> {
+        let handler = self.handlers_mut(pattern).as_mut()?.remove(subscription);
+        self.prune(pattern);
+        handler
+    }
+
+    /// Drops handlers of given pattern if they are empty, pruning nodes left empty.
+    fn prune(&mut self, pattern: &[Segment]) {
+        match pattern.split_first() {
+            None | Some((Segment::Rest, _)) => {
+                let slot = if pattern.is_empty() { &mut self.exact } else { &mut self.rest };
+                if slot.as_ref().is_some_and(Handlers::is_empty) {
+                    *slot = None;
+                }
+            }
+            Some((Segment::Any, tail)) => {
+                if let Some(child) = &mut self.any {
+                    child.prune(tail);
+                    if child.is_empty() {
+                        self.any = None;
+                    }
+                }
+            }
+            Some((Segment::Literal(level), tail)) => {
+                if let Some(child) = self.literal.get_mut(level) {
+                    child.prune(tail);
+                    if child.is_empty() {
+                        self.literal.remove(level);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Collects handlers of patterns matching topic levels: literal levels first, then `+` and
+    /// then `#`.
+    fn matching<'a>(&'a self, topic: &[&str], matched: &mut Vec<&'a Handlers<T>>) {
+        match topic.split_first() {
+            None => matched.extend(self.exact.iter()),
+            Some((level, tail)) => {
+                if let Some(child) = self.literal.get(*level) {
+                    child.matching(tail, matched);
+                }
+                if let Some(child) = &self.any {
+                    child.matching(tail, matched);
+                }
+            }
+        }
+        matched.extend(self.rest.iter());
+    }
+
+    fn matching_mut<'a>(&'a mut self, topic: &[&str], matched: &mut Vec<&'a mut Handlers<T>>) {
+        let Node {
+            exact,
+            rest,
+            literal,
+            any,
+        } = self;
+        match topic.split_first() {
+            None => matched.extend(exact.iter_mut()),
+            Some((level, tail)) => {
+                if let Some(child) = literal.get_mut(*level) {
+                    child.matching_mut(tail, matched);
+                }
+                if let Some(child) = any {
+                    child.matching_mut(tail, matched);
+                }
+            }
+        }
+        matched.extend(rest.iter_mut());
+    }
+}
+
+
+/// Handlers subscribed to MQTT-style topic patterns or to all topics.
+///
+/// Topics are `/`-separated levels. Pattern level `+` matches any single level, and `#` as the
+/// last level matches any number of remaining levels, including none. Patterns are kept in trie,
+/// so emission visits only nodes along emitted topic and wildcards branching from them. Nodes
+/// are pruned once their handlers are found empty, after unsubscription from them or mutable
+/// emission of matching topic.
+#[doc(hidden)]
+pub struct Topics<T> {
+    all: Handlers<T>,
+    root: Node<T>,
+    patterns: HashMap<u64, Vec<Segment>>,
+}
+
+impl<T> Default for Topics<T> {
+    fn default() -> Self {
+        Topics {
+            all: Handlers::default(),
+            root: Node::default(),
+            patterns: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Topics<T> {
+    /// Stores handler subscribed to all topics.
+    pub fn insert<E>(&mut self, priority: i32, handler: T) -> Subscription<E> {
+        self.all.insert(priority, handler)
+    }
+
+    pub fn insert_once<E>(&mut self, priority: i32, handler: T) -> Subscription<E> {
+        self.all.insert_once(priority, handler)
+    }
+
+    pub fn insert_filtered<E>(
+        &mut self,
+        priority: i32,
+        filter: Arc<FilterCounters>,
+        handler: T,
+    ) -> Subscription<E> {
+        self.all.insert_filtered(priority, filter, handler)
+    }
+
+    /// Stores handler subscribed to topics matching given pattern.
+    pub fn insert_topic<E>(
+        &mut self,
+        pattern: &str,
+        priority: i32,
+        handler: T,
+    ) -> Result<Subscription<E>, Error> {
+        let pattern = Segment::parse(pattern)?;
+        let patterns = &mut self.patterns;
+        let handlers = self.root.handlers_mut(&pattern).get_or_insert_with(|| {
+            let handlers = Handlers::default();
+            patterns.insert(handlers.owner(), pattern.clone());
+            handlers
+        });
+        Ok(handlers.insert(priority, handler))
+    }
+
+    pub fn guard<E>(&self, subscription: Subscription<E>) -> SubscriptionGuard<E> {
+        match self.handlers(subscription.as_any()) {
+            Some(handlers) => handlers.guard(subscription),
+            None => self.all.guard(subscription),
+        }
+    }
+
+    pub fn remove(&mut self, subscription: &AnySubscription) -> Option<T> {
+        if subscription.owner == self.all.owner() {
+            return self.all.remove(subscription);
+        }
+        let pattern = self.patterns.get(&subscription.owner)?;
+        let handler = self.root.remove(pattern, subscription);
+        if self.root.handlers(pattern).is_none() {
+            self.patterns.remove(&subscription.owner);
+        }
+        handler
+    }
+
+    pub fn filter_stats(&self, subscription: &AnySubscription) -> Option<FilterStats> {
+        self.handlers(subscription)?.filter_stats(subscription)
+    }
+
+    /// Calls `f` for every handler subscribed to pattern matching `topic` and then for every
+    /// handler subscribed to all topics until it stops propagation.
+    pub fn for_each(
+        &self,
+        topic: &str,
+        mut f: impl FnMut(&Visit, &T) -> Propagation,
+    ) -> Propagation {
+        let levels: Vec<_> = topic.split('/').collect();
+        let mut matched = Vec::new();
+        self.root.matching(&levels, &mut matched);
+        let count = matched.len();
+        for (position, handlers) in matched.into_iter().enumerate() {
+            let followed = position + 1 < count || !self.all.is_empty();
+            if handlers.for_each_followed(followed, &mut f) == Propagation::Stop {
+                return Propagation::Stop;
+            }
+        }
+        self.all.for_each(f)
+    }
+
+    pub fn for_each_mut(
+        &mut self,
+        topic: &str,
+        mut f: impl FnMut(&Visit, &mut T) -> Propagation,
+    ) -> Propagation {
+        let levels: Vec<_> = topic.split('/').collect();
+        let mut matched = Vec::new();
+        self.root.matching_mut(&levels, &mut matched);
+        let count = matched.len();
+        let all_empty = self.all.is_empty();
+        let mut propagation = Propagation::Continue;
+        let mut emptied = Vec::new();
+        for (position, handlers) in matched.into_iter().enumerate() {
+            let followed = position + 1 < count || !all_empty;
+            propagation = handlers.for_each_mut_followed(followed, &mut f);
+            // Handlers called once or detached during emission may leave storage empty.
+            handlers.purge();
+            if handlers.is_empty() {
+                emptied.push(handlers.owner());
+            }
+            if propagation == Propagation::Stop {
+                break;
+            }
+        }
+        for owner in emptied {
+            if let Some(pattern) = self.patterns.remove(&owner) {
+                self.root.prune(&pattern);
+            }
+        }
+        if propagation == Propagation::Stop {
+            return Propagation::Stop;
+        }
+        self.all.for_each_mut(f)
+    }
+
+    /// Returns storage which issued given token.
+    fn handlers(&self, subscription: &AnySubscription) -> Option<&Handlers<T>> {
+        if subscription.owner == self.all.owner() {
+            Some(&self.all)
+        } else {
+            self.root.handlers(self.patterns.get(&subscription.owner)?)
+        }
+    }
+}